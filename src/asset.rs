@@ -1,13 +1,163 @@
 use crate::*;
 use near_sdk_macros::NearSchema;
+use std::collections::{BTreeMap, HashSet};
 
 pub type AssetId = String;
 
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Rejects reports outside of `[min_price, max_price]`.
+pub const VALIDATE_BOUNDS: u32 = 1;
+/// Rejects reports deviating from the current median by more than `max_deviation_bps`.
+pub const VALIDATE_DEVIATION: u32 = 1 << 1;
+/// Rejects reports with decimals other than `decimals`.
+pub const VALIDATE_DECIMALS: u32 = 1 << 2;
+/// Requires at least `min_reporters` fresh reports to serve the median, on top of the quorum.
+pub const VALIDATE_MIN_REPORTERS: u32 = 1 << 3;
+
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, NearSchema)]
 #[serde(crate = "near_sdk::serde")]
 pub struct Asset {
     pub reports: Vec<Report>,
     pub emas: Vec<AssetEma>,
+    /// Daily market sessions. Empty means the asset trades around the clock.
+    pub sessions: Vec<SessionWindow>,
+    /// Fresh reports deviating from the provisional median by more than this many basis points
+    /// are discarded before computing the median. `None` disables the filter.
+    pub outlier_bps: Option<u32>,
+    /// Fresh reports outside of `[Q1 - k * IQR, Q3 + k * IQR]` are discarded before the
+    /// deviation filter, where `k` is given in basis points, e.g. 15000 for 1.5. `None` disables
+    /// the filter.
+    pub outlier_iqr_k_bps: Option<u32>,
+    /// When true, the bare asset ID doesn't serve the spot median, only its EMAs.
+    pub spot_serving_disabled: bool,
+    pub metadata: Option<AssetMetadata>,
+    /// Allows reports with decimals above `MAX_U128_DECIMALS`, where comparisons degrade.
+    pub allow_high_decimals: bool,
+    pub validation: AssetValidation,
+    /// Overrides the contract's `recency_duration_sec` for this asset.
+    pub recency_duration_sec: Option<DurationSec>,
+    /// When true, no price is served for the asset, while reports keep being accepted.
+    pub disabled: bool,
+    pub ema_guard: Option<EmaGuard>,
+    /// Allows negative reports, e.g. for funding rates.
+    pub signed: bool,
+    /// Sanity bounds of the reports. Unlike the `VALIDATE_BOUNDS` check, an out-of-band report
+    /// only rejects the batch in strict mode, and is otherwise skipped.
+    pub min_price: Option<Price>,
+    pub max_price: Option<Price>,
+    /// Allows zero reports, which are otherwise rejected since they break divisions downstream.
+    pub allow_zero_price: bool,
+    /// A new report is warranted once the latest report is this old, see `should_update`.
+    pub heartbeat_sec: Option<DurationSec>,
+    /// A new report is warranted once the price deviates from the median by more than this
+    /// many basis points, see `should_update`.
+    pub deviation_threshold_bps: Option<u32>,
+    /// When the asset was added.
+    pub created_at: Timestamp,
+    /// When the asset last received a report. 0 if it never did.
+    pub last_updated: Timestamp,
+    /// How the fresh reports are aggregated into the spot price served by `get_price_data`.
+    pub aggregation: AggregationMethod,
+}
+
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Default,
+    NearSchema,
+)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub enum AggregationMethod {
+    /// The median after the outlier filters, see `Asset::median_report`.
+    #[default]
+    Median,
+    /// The arithmetic mean, see `Asset::mean_price`.
+    Mean,
+    /// The geometric mean, for multiplicative data like ratios and indices, see
+    /// `Asset::geometric_mean_price`.
+    Geometric,
+}
+
+/// Suppresses the spot price when it deviates from the reference EMA by more than
+/// `max_deviation_bps` basis points.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, NearSchema)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct EmaGuard {
+    pub period_sec: DurationSec,
+    pub max_deviation_bps: u32,
+}
+
+/// Per-asset report validation policy. `flags` is a bitmask of the `VALIDATE_*` checks, each
+/// using its parameters from `params`. A check with a missing parameter is a no-op.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Default, NearSchema)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetValidation {
+    pub flags: u32,
+    pub params: AssetValidationParams,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Default, NearSchema)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetValidationParams {
+    pub min_price: Option<Price>,
+    pub max_price: Option<Price>,
+    pub max_deviation_bps: Option<u32>,
+    /// The decimals every report of the feed is expected to use.
+    pub decimals: Option<u8>,
+    pub min_reporters: Option<u32>,
+}
+
+impl AssetValidation {
+    pub fn is_enabled(&self, flag: u32) -> bool {
+        self.flags & flag != 0
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, NearSchema)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetMetadata {
+    pub symbol: Option<String>,
+    /// Decimals of the underlying token, e.g. 24 for wNEAR.
+    pub token_decimals: Option<u8>,
+    pub description: Option<String>,
+}
+
+/// A daily market session as a range of UTC seconds of the day, `[start_sec, end_sec)`.
+/// A window with `start_sec > end_sec` wraps around midnight.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SessionWindow {
+    pub start_sec: u32,
+    pub end_sec: u32,
+}
+
+impl SessionWindow {
+    pub fn assert_valid(&self) {
+        assert!(
+            u64::from(self.start_sec) < SECONDS_PER_DAY && u64::from(self.end_sec) <= SECONDS_PER_DAY,
+            "Session window is out of the day range"
+        );
+    }
+
+    pub fn contains(&self, timestamp: Timestamp) -> bool {
+        let sec_of_day = (timestamp / 10u64.pow(9) % SECONDS_PER_DAY) as u32;
+        if self.start_sec <= self.end_sec {
+            sec_of_day >= self.start_sec && sec_of_day < self.end_sec
+        } else {
+            sec_of_day >= self.start_sec || sec_of_day < self.end_sec
+        }
+    }
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, NearSchema)]
@@ -23,6 +173,11 @@ pub struct Report {
 pub struct AssetPrice {
     pub asset_id: AssetId,
     pub price: Price,
+    /// When the price was observed off-chain. It's stored as the report timestamp instead of
+    /// the block timestamp, and must be neither in the future nor older than the recency
+    /// duration of the asset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observed_at: Option<Timestamp>,
 }
 
 #[derive(Serialize, Deserialize, NearSchema)]
@@ -30,35 +185,178 @@ pub struct AssetPrice {
 pub struct AssetOptionalPrice {
     pub asset_id: AssetId,
     pub price: Option<Price>,
+    /// Additional context about the returned price. Omitted for regular prices.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<PriceStatus>,
+    /// The fallback asset that served the price, when `asset_id` had no fresh price.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_asset_id: Option<AssetId>,
 }
 
-#[derive(BorshSerialize, BorshDeserialize, NearSchema)]
-pub enum VAsset {
-    V0(AssetV0),
-    Current(Asset),
+/// Whether a price can be served and how fresh it is.
+#[derive(Serialize, Deserialize, Clone, PartialEq, NearSchema)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub enum PriceFreshness {
+    /// The price served by `get_price_data`.
+    Fresh(Price),
+    /// No fresh price, but the median of all the reports and the latest report timestamp.
+    Stale(Price, Timestamp),
+    /// The asset is unknown, not served or was never reported.
+    Unknown,
 }
 
-impl From<VAsset> for Asset {
-    fn from(v: VAsset) -> Self {
-        match v {
-            VAsset::V0(c) => c.into(),
-            VAsset::Current(c) => c,
-        }
-    }
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetPriceFreshness {
+    pub asset_id: AssetId,
+    pub freshness: PriceFreshness,
 }
 
-impl From<Asset> for VAsset {
-    fn from(c: Asset) -> Self {
-        VAsset::Current(c)
-    }
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetDegradedPrice {
+    pub asset_id: AssetId,
+    pub price: Option<Price>,
+    /// True when the price is backed by fewer fresh reports than the quorum requires.
+    pub degraded: bool,
+    pub num_fresh: u32,
 }
 
-impl From<&VAsset> for Asset {
-    fn from(v: &VAsset) -> Self {
-        match v {
-            VAsset::V0(c) => c.clone().into(),
-            VAsset::Current(c) => c.clone(),
-        }
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CombinedPrice {
+    pub asset_id: AssetId,
+    pub spot: Option<Price>,
+    /// Fresh EMA prices by their period in seconds.
+    pub emas: BTreeMap<DurationSec, Price>,
+    /// Timestamp of the latest report of the asset.
+    pub timestamp: Option<Timestamp>,
+}
+
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetCascadePrice {
+    pub asset_id: AssetId,
+    pub price: Option<Price>,
+    /// The recency window that produced the price.
+    pub recency_duration_sec: Option<DurationSec>,
+}
+
+/// An asset ID to price with an optional recency duration overriding the asset's one.
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriceQuery {
+    pub asset_id: AssetId,
+    pub recency_duration_sec: Option<DurationSec>,
+}
+
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetQueryPrice {
+    pub asset_id: AssetId,
+    pub price: Option<Price>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<PriceStatus>,
+    /// The recency duration the price was computed with.
+    pub recency_duration_sec: DurationSec,
+}
+
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetPriceSpread {
+    pub asset_id: AssetId,
+    pub price: Option<Price>,
+    pub min_price: Option<Price>,
+    pub max_price: Option<Price>,
+    /// Number of fresh reports.
+    pub num_reports: u32,
+}
+
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetPriceAge {
+    pub asset_id: AssetId,
+    /// Age in seconds of the report at the median position. `None` if there is no price.
+    pub median_age_sec: Option<DurationSec>,
+    /// Age in seconds of the oldest fresh report used in the aggregation.
+    pub max_age_sec: Option<DurationSec>,
+}
+
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetPriceStats {
+    pub asset_id: AssetId,
+    pub median: Option<Price>,
+    pub mean: Option<Price>,
+    /// Difference between the highest and the lowest fresh reports relative to the median.
+    pub spread_bps: Option<u32>,
+    pub num_fresh: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, NearSchema)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub enum PriceStatus {
+    /// The latest report backing the price was made outside of the asset's market sessions.
+    OffSession,
+    /// The spot price is disabled for the asset, query one of its EMAs instead.
+    SpotDisabled,
+    /// The asset is temporarily disabled, no price is served.
+    Disabled,
+    /// The spot price deviates too much from the asset's reference EMA.
+    EmaDeviation,
+}
+
+/// Describes an asset ID suffix recognized by `get_price_data`.
+#[derive(Serialize, Deserialize, NearSchema)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct SuffixSpec {
+    pub pattern: String,
+    pub description: String,
+    pub example: String,
+}
+
+/// Step-by-step explanation of how a queried asset ID resolves to stored assets.
+#[derive(Serialize, Deserialize, NearSchema)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct ResolutionTrace {
+    pub asset_id: AssetId,
+    pub steps: Vec<String>,
+    pub source_asset_ids: Vec<AssetId>,
+}
+
+/// A stored report, where the oracle ID is interned as its index in
+/// `Contract::oracle_ids_by_index`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, NearSchema)]
+pub struct CompactReport {
+    pub oracle_index: u32,
+    pub timestamp: Timestamp,
+    pub price: Price,
+}
+
+/// A stored asset. The reports of `asset` are empty, they're stored in `reports` instead.
+#[derive(BorshSerialize, BorshDeserialize, Clone, NearSchema)]
+pub struct CompactAsset {
+    pub asset: Asset,
+    pub reports: Vec<CompactReport>,
+}
+
+/// Converting from and to the stored asset requires the oracle indices of the contract, see
+/// `internal_get_asset` and `internal_set_asset`.
+#[derive(BorshSerialize, BorshDeserialize, NearSchema)]
+#[allow(clippy::large_enum_variant)]
+pub enum VAsset {
+    V0(AssetV0),
+    V1(AssetV1),
+    Current(CompactAsset),
+}
+
+impl Default for Asset {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -67,9 +365,136 @@ impl Asset {
         Self {
             reports: Vec::new(),
             emas: Vec::new(),
+            sessions: Vec::new(),
+            outlier_bps: None,
+            outlier_iqr_k_bps: None,
+            spot_serving_disabled: false,
+            metadata: None,
+            allow_high_decimals: false,
+            validation: AssetValidation::default(),
+            recency_duration_sec: None,
+            disabled: false,
+            ema_guard: None,
+            signed: false,
+            min_price: None,
+            max_price: None,
+            allow_zero_price: false,
+            heartbeat_sec: None,
+            deviation_threshold_bps: None,
+            created_at: 0,
+            last_updated: 0,
+            aggregation: AggregationMethod::Median,
         }
     }
 
+    /// Panics if the reported price is not acceptable for this asset.
+    pub fn assert_valid_price(&self, price: &Price) {
+        price.assert_valid();
+        assert!(
+            self.allow_high_decimals || price.decimals <= MAX_U128_DECIMALS,
+            "{}: Price decimals can't exceed {} for this asset",
+            E_INVALID_PRICE,
+            MAX_U128_DECIMALS
+        );
+        assert!(
+            self.signed || !price.negative,
+            "{}: Negative prices are not allowed for this asset",
+            E_INVALID_PRICE
+        );
+        assert!(
+            self.allow_zero_price || !price.is_zero(),
+            "{}: Zero prices are not allowed for this asset",
+            E_INVALID_PRICE
+        );
+    }
+
+    /// Returns true if the price is within the sanity bounds of the asset.
+    pub fn is_within_price_bounds(&self, price: &Price) -> bool {
+        self.min_price.is_none_or(|min_price| *price >= min_price)
+            && self.max_price.is_none_or(|max_price| *price <= max_price)
+    }
+
+    /// Panics if the reported price fails the enabled checks that don't depend on other reports.
+    pub fn assert_validated_price(&self, price: &Price) {
+        let validation = &self.validation;
+        if validation.is_enabled(VALIDATE_BOUNDS) {
+            if let Some(min_price) = validation.params.min_price {
+                assert!(*price >= min_price, "{}: Price is below the asset minimum", E_VALIDATION);
+            }
+            if let Some(max_price) = validation.params.max_price {
+                assert!(*price <= max_price, "{}: Price is above the asset maximum", E_VALIDATION);
+            }
+        }
+        if validation.is_enabled(VALIDATE_DECIMALS) {
+            if let Some(decimals) = validation.params.decimals {
+                assert_eq!(
+                    price.decimals, decimals,
+                    "{}: Price decimals don't match the asset",
+                    E_VALIDATION
+                );
+            }
+        }
+    }
+
+    /// Panics if the deviation check is enabled and the reported price is too far from the
+    /// current median. Passes when there is no median yet.
+    pub fn assert_price_within_deviation(
+        &self,
+        price: &Price,
+        timestamp_cut: Timestamp,
+        min_num_recent_reports: usize,
+    ) {
+        if !self.validation.is_enabled(VALIDATE_DEVIATION) {
+            return;
+        }
+        let band = self
+            .validation
+            .params
+            .max_deviation_bps
+            .zip(self.median_price(timestamp_cut, min_num_recent_reports))
+            .and_then(|(max_deviation_bps, median)| median.deviation_band(max_deviation_bps));
+        if let Some((lower, upper)) = band {
+            assert!(
+                *price >= lower && *price <= upper,
+                "{}: Price deviates too much from the median",
+                E_DEVIATION
+            );
+        }
+    }
+
+    /// Returns true if the price is outside of the EMA guard band. Passes when there is no
+    /// guard or the reference EMA is not fresh.
+    pub fn deviates_from_ema_guard(&self, price: &Price, timestamp_cut: Timestamp) -> bool {
+        let Some(guard) = self.ema_guard.as_ref() else {
+            return false;
+        };
+        self.emas
+            .iter()
+            .find(|ema| ema.period_sec == guard.period_sec && ema.timestamp >= timestamp_cut)
+            .and_then(|ema| ema.price)
+            .and_then(|ema_price| ema_price.deviation_band(guard.max_deviation_bps))
+            .is_some_and(|(lower, upper)| *price < lower || *price > upper)
+    }
+
+    /// Returns the number of fresh reports required to serve the median given the quorum.
+    pub fn min_num_recent_reports(&self, quorum: usize) -> usize {
+        match self.validation.params.min_reporters {
+            Some(min_reporters) if self.validation.is_enabled(VALIDATE_MIN_REPORTERS) => {
+                std::cmp::max(quorum, min_reporters as usize)
+            }
+            _ => quorum,
+        }
+    }
+
+    /// Returns the price reported by the oracle, if it's fresh.
+    pub fn oracle_price(&self, oracle_id: &AccountId, timestamp_cut: Timestamp) -> Option<Price> {
+        self.reports
+            .iter()
+            .find(|report| &report.oracle_id == oracle_id)
+            .filter(|report| report.timestamp >= timestamp_cut)
+            .map(|report| report.price)
+    }
+
     pub fn add_report(&mut self, report: Report) {
         self.reports.push(report);
     }
@@ -80,31 +505,602 @@ impl Asset {
         self.reports.len() != initial_len
     }
 
+    /// Drops the reports made before the cutoff. Returns the number of dropped reports.
+    pub fn prune_reports(&mut self, timestamp_cut: Timestamp) -> u32 {
+        let initial_len = self.reports.len();
+        self.reports.retain(|rp| rp.timestamp >= timestamp_cut);
+        (initial_len - self.reports.len()) as u32
+    }
+
+    /// Returns true if the asset has market sessions and its latest fresh report was made
+    /// outside all of them.
+    pub fn is_reported_off_session(&self, timestamp_cut: Timestamp) -> bool {
+        if self.sessions.is_empty() {
+            return false;
+        }
+        self.reports
+            .iter()
+            .filter(|rp| rp.timestamp >= timestamp_cut)
+            .map(|rp| rp.timestamp)
+            .max()
+            .is_some_and(|timestamp| {
+                !self.sessions.iter().any(|session| session.contains(timestamp))
+            })
+    }
+
     pub fn median_price(
         &self,
         timestamp_cut: Timestamp,
         min_num_recent_reports: usize,
     ) -> Option<Price> {
+        self.active_median_price(timestamp_cut, min_num_recent_reports, &HashSet::new())
+    }
+
+    /// Same as `median_price`, but ignores the reports of the given inactive oracles.
+    pub fn active_median_price(
+        &self,
+        timestamp_cut: Timestamp,
+        min_num_recent_reports: usize,
+        inactive_oracle_ids: &HashSet<AccountId>,
+    ) -> Option<Price> {
+        self.median_report(timestamp_cut, min_num_recent_reports, inactive_oracle_ids)
+            .map(|rp| rp.price)
+    }
+
+    /// Returns the fresh report at the median position, after discarding the outliers and the
+    /// reports of inactive oracles.
+    pub fn median_report(
+        &self,
+        timestamp_cut: Timestamp,
+        min_num_recent_reports: usize,
+        inactive_oracle_ids: &HashSet<AccountId>,
+    ) -> Option<&Report> {
         let mut recent_reports: Vec<_> = self
             .reports
             .iter()
-            .filter(|rp| rp.timestamp >= timestamp_cut)
+            .filter(|rp| {
+                rp.timestamp >= timestamp_cut && !inactive_oracle_ids.contains(&rp.oracle_id)
+            })
+            .collect();
+        if recent_reports.len() < min_num_recent_reports {
+            return None;
+        }
+        if let Some(k_bps) = self.outlier_iqr_k_bps {
+            let q1 = select_percentile(&mut recent_reports, 25)?.price;
+            let q3 = select_percentile(&mut recent_reports, 75)?.price;
+            if let Some((lower, upper)) = iqr_band(&q1, &q3, k_bps) {
+                recent_reports.retain(|rp| rp.price >= lower && rp.price <= upper);
+                if recent_reports.len() < min_num_recent_reports {
+                    return None;
+                }
+            }
+        }
+        let median = select_median(&mut recent_reports)?;
+        if let Some((lower, upper)) = self
+            .outlier_bps
+            .and_then(|outlier_bps| median.price.deviation_band(outlier_bps))
+        {
+            recent_reports.retain(|rp| rp.price >= lower && rp.price <= upper);
+            if recent_reports.len() < min_num_recent_reports {
+                return None;
+            }
+            return select_median(&mut recent_reports);
+        }
+        Some(median)
+    }
+
+    /// Returns the price at the given percentile of the fresh reports of active oracles.
+    pub fn percentile_price(
+        &self,
+        percentile: u8,
+        timestamp_cut: Timestamp,
+        min_num_recent_reports: usize,
+        inactive_oracle_ids: &HashSet<AccountId>,
+    ) -> Option<Price> {
+        let mut recent_reports: Vec<_> = self
+            .reports
+            .iter()
+            .filter(|rp| {
+                rp.timestamp >= timestamp_cut && !inactive_oracle_ids.contains(&rp.oracle_id)
+            })
             .collect();
         if recent_reports.len() < min_num_recent_reports {
             return None;
         }
-        let index = recent_reports.len() / 2;
-        recent_reports.select_nth_unstable_by(index, |a, b| a.price.cmp(&b.price));
-        recent_reports.get(index).map(|tp| tp.price)
+        select_percentile(&mut recent_reports, percentile).map(|rp| rp.price)
+    }
+
+    /// Returns the ages in seconds of the median report and of the oldest fresh report at the
+    /// given timestamp, if the median can be served.
+    pub fn report_ages(
+        &self,
+        asset_id: AssetId,
+        timestamp: Timestamp,
+        timestamp_cut: Timestamp,
+        min_num_recent_reports: usize,
+        inactive_oracle_ids: &HashSet<AccountId>,
+    ) -> AssetPriceAge {
+        let age_sec = |report_timestamp: Timestamp| {
+            (timestamp.saturating_sub(report_timestamp) / 10u64.pow(9)) as DurationSec
+        };
+        let median_report =
+            self.median_report(timestamp_cut, min_num_recent_reports, inactive_oracle_ids);
+        AssetPriceAge {
+            asset_id,
+            median_age_sec: median_report.map(|rp| age_sec(rp.timestamp)),
+            max_age_sec: median_report.and_then(|_| {
+                self.reports
+                    .iter()
+                    .filter(|rp| !inactive_oracle_ids.contains(&rp.oracle_id))
+                    .map(|rp| rp.timestamp)
+                    .filter(|report_timestamp| *report_timestamp >= timestamp_cut)
+                    .min()
+                    .map(age_sec)
+            }),
+        }
+    }
+
+    /// Returns the lowest and the highest fresh report prices of active oracles, and the number
+    /// of these reports.
+    pub fn fresh_price_range(
+        &self,
+        timestamp_cut: Timestamp,
+        inactive_oracle_ids: &HashSet<AccountId>,
+    ) -> Option<(Price, Price, u32)> {
+        let recent_prices: Vec<Price> = self
+            .reports
+            .iter()
+            .filter(|rp| {
+                rp.timestamp >= timestamp_cut && !inactive_oracle_ids.contains(&rp.oracle_id)
+            })
+            .map(|rp| rp.price)
+            .collect();
+        let min_price = recent_prices.iter().min()?;
+        let max_price = recent_prices.iter().max()?;
+        Some((*min_price, *max_price, recent_prices.len() as u32))
+    }
+
+    /// Returns the geometric mean of the fresh reports of active oracles, normalized to the
+    /// largest reported decimals and rounded down. Returns `None` if there are not enough fresh
+    /// reports, any report is zero or negative, or the product of the reports overflows.
+    pub fn geometric_mean_price(
+        &self,
+        timestamp_cut: Timestamp,
+        min_num_recent_reports: usize,
+        inactive_oracle_ids: &HashSet<AccountId>,
+    ) -> Option<Price> {
+        let recent_reports: Vec<_> = self
+            .reports
+            .iter()
+            .filter(|rp| {
+                rp.timestamp >= timestamp_cut && !inactive_oracle_ids.contains(&rp.oracle_id)
+            })
+            .collect();
+        if recent_reports.len() < min_num_recent_reports {
+            return None;
+        }
+        let decimals = recent_reports.iter().map(|rp| rp.price.decimals).max()?;
+        let mut product = 1u128;
+        let mut min_multiplier = u128::MAX;
+        let mut max_multiplier = 0u128;
+        for rp in recent_reports.iter() {
+            if rp.price.multiplier == 0 || rp.price.is_negative() {
+                return None;
+            }
+            let multiplier = rp.price.rescale(decimals)?.multiplier;
+            product = product.checked_mul(multiplier)?;
+            min_multiplier = std::cmp::min(min_multiplier, multiplier);
+            max_multiplier = std::cmp::max(max_multiplier, multiplier);
+        }
+        let n = u32::try_from(recent_reports.len()).ok()?;
+        // The mean lies between the lowest and the highest report, so search for the largest
+        // root in there whose n-th power doesn't exceed the product.
+        let (mut lower, mut upper) = (min_multiplier, max_multiplier);
+        while lower < upper {
+            let mid = lower + (upper - lower).div_ceil(2);
+            match mid.checked_pow(n) {
+                Some(power) if power <= product => lower = mid,
+                _ => upper = mid - 1,
+            }
+        }
+        Some(Price {
+            multiplier: lower,
+            decimals,
+            negative: false,
+        })
+    }
+
+    /// Returns the arithmetic mean of the fresh reports of active oracles, normalized to the largest reported
+    /// decimals. Returns `None` if there are not enough fresh reports, any report is negative,
+    /// or the sum overflows.
+    pub fn mean_price(
+        &self,
+        timestamp_cut: Timestamp,
+        min_num_recent_reports: usize,
+        inactive_oracle_ids: &HashSet<AccountId>,
+    ) -> Option<Price> {
+        let recent_reports: Vec<_> = self
+            .reports
+            .iter()
+            .filter(|rp| {
+                rp.timestamp >= timestamp_cut && !inactive_oracle_ids.contains(&rp.oracle_id)
+            })
+            .collect();
+        if recent_reports.len() < min_num_recent_reports {
+            return None;
+        }
+        let decimals = recent_reports.iter().map(|rp| rp.price.decimals).max()?;
+        let mut sum = 0u128;
+        for rp in recent_reports.iter() {
+            if rp.price.is_negative() {
+                return None;
+            }
+            sum = sum.checked_add(rp.price.rescale(decimals)?.multiplier)?;
+        }
+        Some(Price {
+            multiplier: sum / recent_reports.len() as u128,
+            decimals,
+            negative: false,
+        })
+    }
+
+    /// Returns the spot price of the fresh reports of active oracles using the asset's
+    /// aggregation method.
+    pub fn aggregated_price(
+        &self,
+        timestamp_cut: Timestamp,
+        min_num_recent_reports: usize,
+        inactive_oracle_ids: &HashSet<AccountId>,
+    ) -> Option<Price> {
+        match self.aggregation {
+            AggregationMethod::Median => {
+                self.active_median_price(timestamp_cut, min_num_recent_reports, inactive_oracle_ids)
+            }
+            AggregationMethod::Mean => {
+                self.mean_price(timestamp_cut, min_num_recent_reports, inactive_oracle_ids)
+            }
+            AggregationMethod::Geometric => self.geometric_mean_price(
+                timestamp_cut,
+                min_num_recent_reports,
+                inactive_oracle_ids,
+            ),
+        }
     }
+
+    /// Returns the median and mean of the fresh reports of active oracles along with their
+    /// spread.
+    pub fn price_stats(
+        &self,
+        asset_id: AssetId,
+        timestamp_cut: Timestamp,
+        min_num_recent_reports: usize,
+        inactive_oracle_ids: &HashSet<AccountId>,
+    ) -> AssetPriceStats {
+        let median =
+            self.active_median_price(timestamp_cut, min_num_recent_reports, inactive_oracle_ids);
+        let range = self.fresh_price_range(timestamp_cut, inactive_oracle_ids);
+        let spread_bps = median.zip(range).and_then(|(median, (min_price, max_price, _))| {
+            let decimals = std::cmp::max(median.decimals, max_price.decimals);
+            let decimals = std::cmp::max(decimals, min_price.decimals);
+            let median = median.rescale(decimals)?.multiplier;
+            let spread = max_price.rescale(decimals)?.multiplier
+                - min_price.rescale(decimals)?.multiplier;
+            if median == 0 {
+                return None;
+            }
+            u32::try_from(spread.checked_mul(10000)? / median).ok()
+        });
+        AssetPriceStats {
+            asset_id,
+            median,
+            mean: self.mean_price(timestamp_cut, min_num_recent_reports, inactive_oracle_ids),
+            spread_bps,
+            num_fresh: range.map_or(0, |(_, _, num_reports)| num_reports),
+        }
+    }
+}
+
+fn select_median<'a>(reports: &mut [&'a Report]) -> Option<&'a Report> {
+    select_percentile(reports, 50)
+}
+
+/// Returns the report at the given percentile of the prices. The 50th percentile is the
+/// median, the 100th is the highest price.
+fn select_percentile<'a>(reports: &mut [&'a Report], percentile: u8) -> Option<&'a Report> {
+    if reports.is_empty() {
+        return None;
+    }
+    let index = std::cmp::min(reports.len() * percentile as usize / 100, reports.len() - 1);
+    reports.select_nth_unstable_by(index, |a, b| a.price.cmp(&b.price));
+    reports.get(index).copied()
+}
+
+/// Returns `[Q1 - k * IQR, Q3 + k * IQR]` with `k` in basis points, expressed with 4 more
+/// decimals than the quartiles so the multiplier doesn't truncate. Returns `None` on overflow.
+fn iqr_band(q1: &Price, q3: &Price, k_bps: u32) -> Option<(Price, Price)> {
+    let decimals = std::cmp::max(q1.decimals, q3.decimals);
+    let to_signed = |price: &Price| -> Option<i128> {
+        let value = i128::try_from(price.rescale(decimals)?.multiplier).ok()?;
+        Some(if price.is_negative() { -value } else { value })
+    };
+    let to_price = |value: i128| -> Option<Price> {
+        Some(Price {
+            multiplier: value.unsigned_abs(),
+            decimals: decimals.checked_add(4)?,
+            negative: value < 0,
+        })
+    };
+    let (q1, q3) = (to_signed(q1)?, to_signed(q3)?);
+    let margin = q3.checked_sub(q1)?.checked_mul(i128::from(k_bps))?;
+    let lower = q1.checked_mul(10000)?.checked_sub(margin)?;
+    let upper = q3.checked_mul(10000)?.checked_add(margin)?;
+    Some((to_price(lower)?, to_price(upper)?))
 }
 
 impl Contract {
     pub fn internal_get_asset(&self, asset_id: &AssetId) -> Option<Asset> {
-        self.assets.get(asset_id).map(|v| v.into())
+        self.assets.get(asset_id).map(|v_asset| match v_asset {
+            VAsset::V0(asset) => asset.clone().into(),
+            VAsset::V1(asset) => asset.clone().into(),
+            VAsset::Current(CompactAsset { asset, reports }) => Asset {
+                reports: reports
+                    .iter()
+                    .filter_map(|rp| {
+                        Some(Report {
+                            oracle_id: self.oracle_ids_by_index.get(rp.oracle_index)?.clone(),
+                            timestamp: rp.timestamp,
+                            price: rp.price,
+                        })
+                    })
+                    .collect(),
+                ..asset.clone()
+            },
+        })
+    }
+
+    /// Returns a page of the assets whose IDs pass the filter. The index and the limit apply
+    /// to the filtered IDs.
+    pub(crate) fn internal_get_assets_page(
+        &self,
+        filter: impl Fn(&AssetId) -> bool,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<(AssetId, Asset)> {
+        self.assets
+            .keys()
+            .filter(|asset_id| filter(asset_id))
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.map_or(usize::MAX, |limit| limit as usize))
+            .filter_map(|asset_id| Some((asset_id.clone(), self.internal_get_asset(asset_id)?)))
+            .collect()
+    }
+
+    /// Returns the freshness cutoff of the queried asset ID at the given timestamp, using the
+    /// asset's recency duration if it overrides the contract's one.
+    pub(crate) fn internal_timestamp_cut(&self, asset_id: &str, timestamp: Timestamp) -> Timestamp {
+        timestamp.saturating_sub(to_nano(self.internal_recency_duration_sec(asset_id)))
+    }
+
+    /// Returns the recency duration of the queried asset ID.
+    pub(crate) fn internal_recency_duration_sec(&self, asset_id: &str) -> DurationSec {
+        let base_asset_id = self
+            .internal_parse_ema_query(asset_id)
+            .map_or(asset_id, |(base, _)| base);
+        self.internal_get_asset(&self.internal_resolve_asset_id(base_asset_id))
+            .and_then(|asset| asset.recency_duration_sec)
+            .unwrap_or(self.recency_duration_sec)
+    }
+
+    /// Splits an EMA query, e.g. `wrap.near#3600`, into the base asset ID and the EMA period.
+    /// Returns `None` unless the base asset exists and has an EMA with this period, in which
+    /// case the asset ID is looked up literally, since asset IDs may contain `#`.
+    pub(crate) fn internal_parse_ema_query<'a>(
+        &self,
+        asset_id: &'a str,
+    ) -> Option<(&'a str, DurationSec)> {
+        let (base_asset_id, period_sec) = asset_id.rsplit_once('#')?;
+        let period_sec: DurationSec = period_sec.parse().ok().filter(|period_sec| *period_sec > 0)?;
+        let asset = self.internal_get_asset(&self.internal_resolve_asset_id(base_asset_id))?;
+        asset
+            .emas
+            .iter()
+            .any(|ema| ema.period_sec == period_sec)
+            .then_some((base_asset_id, period_sec))
+    }
+
+    /// Follows the aliases of the given asset ID and returns the ID it ends up at, or the ID
+    /// itself. Aliases can't form cycles, see `set_asset_alias`.
+    pub fn internal_resolve_asset_id(&self, asset_id: &str) -> AssetId {
+        let mut asset_id = asset_id.to_string();
+        while let Some(target_id) = self.asset_aliases.get(&asset_id) {
+            asset_id = target_id.clone();
+        }
+        asset_id
+    }
+
+    /// Stores the asset, interning the oracle IDs of its reports.
+    pub fn internal_set_asset(&mut self, asset_id: &AssetId, mut asset: Asset) {
+        let reports = std::mem::take(&mut asset.reports)
+            .into_iter()
+            .map(|rp| CompactReport {
+                oracle_index: self.internal_oracle_index(&rp.oracle_id),
+                timestamp: rp.timestamp,
+                price: rp.price,
+            })
+            .collect();
+        self.assets
+            .insert(asset_id.clone(), VAsset::Current(CompactAsset { asset, reports }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn asset_with_prices(prices: &[Price]) -> Asset {
+        let mut asset = Asset::new();
+        for (i, price) in prices.iter().enumerate() {
+            asset.add_report(Report {
+                oracle_id: format!("oracle{}.near", i).parse().unwrap(),
+                timestamp: 100,
+                price: *price,
+            });
+        }
+        asset
+    }
+
+    fn p(multiplier: u128, decimals: u8) -> Price {
+        Price {
+            multiplier,
+            decimals,
+            negative: false,
+        }
+    }
+
+    #[test]
+    fn test_geometric_mean_vs_arithmetic_mean() {
+        let asset = asset_with_prices(&[p(1, 0), p(4, 0), p(16, 0)]);
+        let geometric = asset.geometric_mean_price(0, 1, &HashSet::new()).unwrap();
+        assert_eq!(geometric, p(4, 0));
+        // The arithmetic mean of the same set is 7.
+        assert!(geometric < p(7, 0));
+    }
+
+    #[test]
+    fn test_geometric_mean_normalizes_decimals() {
+        let asset = asset_with_prices(&[p(10, 1), p(4, 0)]);
+        assert_eq!(asset.geometric_mean_price(0, 1, &HashSet::new()), Some(p(20, 1)));
+    }
+
+    #[test]
+    fn test_median_outlier_rejection() {
+        let mut asset = asset_with_prices(&[p(100, 0), p(102, 0), p(104, 0), p(200, 0)]);
+        assert_eq!(asset.median_price(0, 1), Some(p(104, 0)));
+
+        asset.outlier_bps = Some(1000);
+        assert_eq!(asset.median_price(0, 1), Some(p(102, 0)));
+        // Not enough reports left after dropping the outlier.
+        assert_eq!(asset.median_price(0, 4), None);
+    }
+
+    #[test]
+    fn test_median_iqr_outlier_rejection() {
+        let mut asset =
+            asset_with_prices(&[p(100, 0), p(101, 0), p(102, 0), p(103, 0), p(104, 0), p(125, 0)]);
+        // A band wide enough for a noisy feed keeps the outlier.
+        asset.outlier_bps = Some(3000);
+        assert_eq!(asset.median_price(0, 1), Some(p(103, 0)));
+
+        asset.outlier_iqr_k_bps = Some(15000);
+        assert_eq!(asset.median_price(0, 1), Some(p(102, 0)));
+        asset.outlier_bps = None;
+        assert_eq!(asset.median_price(0, 1), Some(p(102, 0)));
+        assert_eq!(asset.median_price(0, 6), None);
+    }
+
+    #[test]
+    fn test_median_iqr_band_edges() {
+        // Q1 is 101 and Q3 is 104, so k = 1 keeps [98, 107].
+        let mut asset =
+            asset_with_prices(&[p(100, 0), p(1010, 1), p(102, 0), p(103, 0), p(104, 0), p(107, 0)]);
+        asset.outlier_iqr_k_bps = Some(10000);
+        assert_eq!(asset.median_price(0, 6), Some(p(103, 0)));
+
+        let mut asset =
+            asset_with_prices(&[p(100, 0), p(1010, 1), p(102, 0), p(103, 0), p(104, 0), p(10701, 2)]);
+        asset.outlier_iqr_k_bps = Some(10000);
+        assert_eq!(asset.median_price(0, 6), None);
+        assert_eq!(asset.median_price(0, 5), Some(p(102, 0)));
+    }
+
+    #[test]
+    fn test_median_iqr_negative_prices() {
+        let n = |multiplier| Price {
+            multiplier,
+            decimals: 0,
+            negative: true,
+        };
+        let mut asset = asset_with_prices(&[n(5), n(4), n(3), n(2), n(1), p(20, 0)]);
+        asset.outlier_iqr_k_bps = Some(15000);
+        assert_eq!(asset.median_price(0, 1), Some(n(3)));
+    }
+
+    #[test]
+    fn test_mean_price() {
+        let asset = asset_with_prices(&[p(10, 1), p(4, 0), p(25, 1)]);
+        assert_eq!(asset.mean_price(0, 1, &HashSet::new()), Some(p(25, 1)));
+        assert_eq!(asset.mean_price(0, 4, &HashSet::new()), None);
+    }
+
+    #[test]
+    fn test_geometric_mean_zero_report() {
+        let asset = asset_with_prices(&[p(0, 0), p(4, 0), p(16, 0)]);
+        assert_eq!(asset.geometric_mean_price(0, 1, &HashSet::new()), None);
+    }
+
+    #[test]
+    fn test_reports_store_oracle_indices() {
+        use crate::tests::*;
+        use near_sdk::test_utils::accounts;
+
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_oracle(&mut contract, &accounts(2));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+        report(&mut contract, &accounts(2), ts(10), &[("wrap.near", price(7, 0))]);
+        match contract.assets.get("wrap.near") {
+            Some(VAsset::Current(asset)) => {
+                let indices: Vec<_> = asset.reports.iter().map(|rp| rp.oracle_index).collect();
+                assert_eq!(indices, vec![0, 1]);
+            }
+            _ => panic!("Asset is not compact"),
+        }
+
+        set_context(accounts(0), ts(10));
+        contract.remove_oracle(accounts(1));
+        let oracle_ids: Vec<_> = contract
+            .get_asset_reports("wrap.near".to_string(), None)
+            .into_iter()
+            .map(|rp| rp.oracle_id)
+            .collect();
+        assert_eq!(oracle_ids, vec![accounts(1), accounts(2)]);
+
+        add_test_oracle(&mut contract, &accounts(3));
+        add_test_oracle(&mut contract, &accounts(1));
+        let oracle_ids: Vec<_> = contract.oracle_ids_by_index.iter().cloned().collect();
+        assert_eq!(oracle_ids, vec![accounts(1), accounts(2), accounts(3)]);
+        assert_eq!(contract.oracle_indices.get(&accounts(3)), Some(&2));
+    }
+
+    #[test]
+    fn test_geometric_mean_rounds_down() {
+        let asset = asset_with_prices(&[p(2, 0), p(3, 0)]);
+        assert_eq!(asset.geometric_mean_price(0, 1, &HashSet::new()), Some(p(2, 0)));
+        let asset = asset_with_prices(&[p(200, 2), p(3, 0)]);
+        assert_eq!(asset.geometric_mean_price(0, 1, &HashSet::new()), Some(p(244, 2)));
+    }
+
+    #[test]
+    fn test_geometric_mean_overflow() {
+        let asset = asset_with_prices(&[p(u128::MAX / 2, 0), p(4, 0)]);
+        assert_eq!(asset.geometric_mean_price(0, 1, &HashSet::new()), None);
+    }
+
+    #[test]
+    fn test_aggregated_price() {
+        let mut asset = asset_with_prices(&[p(1, 0), p(2, 0), p(32, 0)]);
+        assert_eq!(asset.aggregated_price(0, 1, &HashSet::new()), Some(p(2, 0)));
+        asset.aggregation = AggregationMethod::Mean;
+        assert_eq!(asset.aggregated_price(0, 1, &HashSet::new()), Some(p(11, 0)));
+        asset.aggregation = AggregationMethod::Geometric;
+        assert_eq!(asset.aggregated_price(0, 1, &HashSet::new()), Some(p(4, 0)));
     }
 
-    pub fn internal_set_asset(&mut self, asset_id: &AssetId, asset: Asset) {
-        self.assets.insert(asset_id.clone(), asset.into());
+    #[test]
+    fn test_geometric_mean_quorum() {
+        let asset = asset_with_prices(&[p(4, 0), p(16, 0)]);
+        assert_eq!(asset.geometric_mean_price(0, 3, &HashSet::new()), None);
+        assert_eq!(asset.geometric_mean_price(101, 1, &HashSet::new()), None);
     }
 }
@@ -0,0 +1,120 @@
+use crate::*;
+use near_sdk_macros::NearSchema;
+
+/// Number of the latest owner actions kept in the audit log.
+pub const MAX_OWNER_ACTIONS: u64 = 256;
+
+/// Audit log entry of a sensitive owner or admin operation.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, NearSchema)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct OwnerAction {
+    pub actor: AccountId,
+    /// Name of the called method, e.g. `approve_codehash`.
+    pub action: String,
+    /// The codehash, checksum, account or asset the action applies to. Renames and aliases
+    /// are logged as `old_id -> new_id`.
+    pub target: String,
+    pub timestamp: Timestamp,
+}
+
+#[near]
+impl Contract {
+    /// Returns a page of the latest `MAX_OWNER_ACTIONS` owner actions, oldest first.
+    pub fn get_owner_actions(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<OwnerAction> {
+        let first_index = self.num_owner_actions.saturating_sub(MAX_OWNER_ACTIONS);
+        let from_index = first_index.saturating_add(from_index.unwrap_or(0));
+        let end_index = from_index
+            .saturating_add(limit.unwrap_or(MAX_OWNER_ACTIONS))
+            .min(self.num_owner_actions);
+        (from_index..end_index)
+            .filter_map(|index| self.owner_actions.get(&index).cloned())
+            .collect()
+    }
+}
+
+impl Contract {
+    /// Appends the action of the caller to the audit log, dropping the oldest entry once the
+    /// log holds `MAX_OWNER_ACTIONS` entries.
+    pub(crate) fn internal_log_owner_action(&mut self, action: &str, target: impl ToString) {
+        let index = self.num_owner_actions;
+        if index >= MAX_OWNER_ACTIONS {
+            self.owner_actions.remove(&(index - MAX_OWNER_ACTIONS));
+        }
+        self.owner_actions.insert(
+            index,
+            OwnerAction {
+                actor: env::predecessor_account_id(),
+                action: action.to_string(),
+                target: target.to_string(),
+                timestamp: env::block_timestamp(),
+            },
+        );
+        self.num_owner_actions += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::*;
+    use crate::*;
+    use near_sdk::test_utils::accounts;
+
+    #[test]
+    fn test_owner_actions() {
+        let mut contract = setup();
+        set_context(accounts(0), ts(10));
+        contract.approve_codehash("ab".repeat(32));
+
+        let actions = contract.get_owner_actions(Some(1), None);
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].actor, accounts(0));
+        assert_eq!(actions[0].action, "approve_codehash");
+        assert_eq!(actions[0].target, "ab".repeat(32));
+        assert_eq!(actions[0].timestamp, ts(10));
+        assert_eq!(contract.get_owner_actions(None, None).len(), 2);
+    }
+
+    #[test]
+    fn test_asset_and_oracle_actions_logged() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        contract.set_oracle_active(accounts(1), false);
+        contract.rename_asset("wrap.near".to_string(), "wnear.near".to_string(), None, None);
+        contract.set_asset_alias("wrap.near".to_string(), "wnear.near".to_string());
+        contract.remove_asset_alias("wrap.near".to_string());
+        contract.remove_asset("wnear.near".to_string());
+        contract.propose_new_owner(accounts(2));
+
+        let actions: Vec<_> = contract
+            .get_owner_actions(Some(1), None)
+            .into_iter()
+            .map(|action| (action.action, action.target))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                ("add_asset".to_string(), "wrap.near".to_string()),
+                ("set_oracle_active".to_string(), accounts(1).to_string()),
+                ("rename_asset".to_string(), "wrap.near -> wnear.near".to_string()),
+                ("set_asset_alias".to_string(), "wrap.near -> wnear.near".to_string()),
+                ("remove_asset_alias".to_string(), "wrap.near".to_string()),
+                ("remove_asset".to_string(), "wnear.near".to_string()),
+                ("propose_new_owner".to_string(), accounts(2).to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_owner_actions_bounded() {
+        let mut contract = setup();
+        for i in 0..MAX_OWNER_ACTIONS {
+            contract.approve_checksum(i.to_string());
+        }
+        let actions = contract.get_owner_actions(None, None);
+        assert_eq!(actions.len() as u64, MAX_OWNER_ACTIONS);
+        assert_eq!(actions[0].target, "0");
+        assert_eq!(contract.get_owner_actions(Some(1), Some(1))[0].target, "1");
+    }
+}
@@ -0,0 +1,225 @@
+use crate::*;
+
+/// Minimum time between the last report of an oracle and the withdrawal of its bond, so the
+/// owner has time to slash it for its latest reports.
+pub const BOND_WITHDRAWAL_COOLDOWN_SEC: DurationSec = 7 * 24 * 60 * 60;
+
+/// Bond left by a removed oracle, along with the timestamp of its last report.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct RemovedOracleBond {
+    pub bond: NearToken,
+    pub last_report: Timestamp,
+}
+
+#[near]
+impl Contract {
+    /// Adds the attached deposit to the bond of the calling oracle.
+    #[payable]
+    pub fn deposit_bond(&mut self) -> NearToken {
+        let amount = env::attached_deposit();
+        assert!(!amount.is_zero(), "Requires a deposit");
+        let oracle_id = env::predecessor_account_id();
+        let mut oracle = self.internal_get_oracle(&oracle_id).expect("Not an oracle");
+        oracle.bond = oracle.bond.saturating_add(amount);
+        self.total_bonded = self.total_bonded.saturating_add(amount);
+        let bond = oracle.bond;
+        self.internal_set_oracle(&oracle_id, oracle);
+        bond
+    }
+
+    /// Deducts `amount` from the bond of the oracle for provable misbehavior, including the
+    /// bond a removed oracle hasn't withdrawn yet. The slashed NEAR stays on the contract and
+    /// funds the NEAR claims.
+    #[payable]
+    pub fn slash_oracle(&mut self, account_id: AccountId, amount: U128, reason: String) {
+        assert_one_yocto();
+        self.assert_owner();
+        let amount = NearToken::from_yoctonear(amount.0);
+        if let Some(mut oracle) = self.internal_get_oracle(&account_id) {
+            assert!(amount <= oracle.bond, "Slash amount exceeds the bond");
+            oracle.bond = oracle.bond.saturating_sub(amount);
+            self.internal_set_oracle(&account_id, oracle);
+        } else {
+            let removed_bond = self
+                .removed_oracle_bonds
+                .get_mut(&account_id)
+                .expect("Not an oracle");
+            assert!(amount <= removed_bond.bond, "Slash amount exceeds the bond");
+            removed_bond.bond = removed_bond.bond.saturating_sub(amount);
+        }
+        self.total_bonded = self.total_bonded.saturating_sub(amount);
+        self.internal_log_owner_action("slash_oracle", &account_id);
+        log!(
+            "EVENT_JSON:{}",
+            serde_json::json!({
+                "standard": "price-oracle",
+                "version": "1.0.0",
+                "event": "oracle_slashed",
+                "data": [{
+                    "oracle_id": account_id,
+                    "amount": U128(amount.as_yoctonear()),
+                    "reason": reason,
+                }]
+            })
+        );
+    }
+
+    /// Withdraws the whole bond of the calling oracle, once `BOND_WITHDRAWAL_COOLDOWN_SEC`
+    /// has passed since its last report. A removed oracle withdraws the bond it left.
+    #[payable]
+    pub fn withdraw_bond(&mut self) -> Promise {
+        assert_one_yocto();
+        let oracle_id = env::predecessor_account_id();
+        let (bond, last_report) = match self.removed_oracle_bonds.remove(&oracle_id) {
+            Some(removed_bond) => (removed_bond.bond, removed_bond.last_report),
+            None => {
+                let mut oracle = self.internal_get_oracle(&oracle_id).expect("Not an oracle");
+                let bond = std::mem::replace(&mut oracle.bond, NearToken::from_yoctonear(0));
+                let last_report = oracle.last_report;
+                self.internal_set_oracle(&oracle_id, oracle);
+                (bond, last_report)
+            }
+        };
+        assert!(!bond.is_zero(), "No bond to withdraw");
+        assert!(
+            env::block_timestamp() >= last_report + to_nano(BOND_WITHDRAWAL_COOLDOWN_SEC),
+            "The bond is locked for {} seconds after the last report",
+            BOND_WITHDRAWAL_COOLDOWN_SEC
+        );
+        self.total_bonded = self.total_bonded.saturating_sub(bond);
+        Promise::new(oracle_id).transfer(bond)
+    }
+
+    /// Returns the bond a removed oracle can still withdraw.
+    pub fn get_removed_oracle_bond(&self, account_id: AccountId) -> Option<NearToken> {
+        self.removed_oracle_bonds
+            .get(&account_id)
+            .map(|removed_bond| removed_bond.bond)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::*;
+    use crate::*;
+    use near_sdk::test_utils::accounts;
+
+    fn deposit_bond(contract: &mut Contract, oracle_id: &AccountId, amount: NearToken) {
+        let timestamp = env::block_timestamp();
+        set_context_with_deposit(oracle_id.clone(), timestamp, amount);
+        contract.deposit_bond();
+        set_context(contract.owner_id.clone(), timestamp);
+    }
+
+    fn bond(contract: &Contract, oracle_id: &AccountId) -> NearToken {
+        contract.get_oracle(oracle_id.clone()).unwrap().bond
+    }
+
+    #[test]
+    fn test_deposit_and_slash_bond() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        deposit_bond(&mut contract, &accounts(1), NearToken::from_near(3));
+        deposit_bond(&mut contract, &accounts(1), NearToken::from_near(2));
+        assert_eq!(bond(&contract, &accounts(1)), NearToken::from_near(5));
+        assert_eq!(contract.total_bonded, NearToken::from_near(5));
+
+        contract.slash_oracle(
+            accounts(1),
+            U128(NearToken::from_near(2).as_yoctonear()),
+            "Reported a stale price".to_string(),
+        );
+        assert_eq!(bond(&contract, &accounts(1)), NearToken::from_near(3));
+        assert_eq!(contract.total_bonded, NearToken::from_near(3));
+        assert!(near_sdk::test_utils::get_logs()[0].contains("oracle_slashed"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Slash amount exceeds the bond")]
+    fn test_slash_more_than_bond() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        deposit_bond(&mut contract, &accounts(1), NearToken::from_near(1));
+        contract.slash_oracle(
+            accounts(1),
+            U128(NearToken::from_near(2).as_yoctonear()),
+            "Reported a stale price".to_string(),
+        );
+    }
+
+    #[test]
+    fn test_withdraw_bond_after_cooldown() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        deposit_bond(&mut contract, &accounts(1), NearToken::from_near(5));
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+
+        set_context(accounts(1), ts(10 + BOND_WITHDRAWAL_COOLDOWN_SEC));
+        contract.withdraw_bond();
+        assert_eq!(bond(&contract, &accounts(1)), NearToken::from_yoctonear(0));
+        assert_eq!(contract.total_bonded, NearToken::from_yoctonear(0));
+        let receipts = near_sdk::test_utils::get_created_receipts();
+        assert_eq!(receipts[0].receiver_id, accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "The bond is locked for 604800 seconds after the last report")]
+    fn test_withdraw_bond_during_cooldown() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        deposit_bond(&mut contract, &accounts(1), NearToken::from_near(5));
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+
+        set_context(accounts(1), ts(10 + BOND_WITHDRAWAL_COOLDOWN_SEC - 1));
+        contract.withdraw_bond();
+    }
+
+    #[test]
+    fn test_remove_oracle_keeps_bond_locked() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        deposit_bond(&mut contract, &accounts(1), NearToken::from_near(5));
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+
+        set_context(accounts(0), ts(20));
+        contract.remove_oracle(accounts(1));
+        assert!(near_sdk::test_utils::get_created_receipts().is_empty());
+        assert_eq!(contract.total_bonded, NearToken::from_near(5));
+        assert_eq!(
+            contract.get_removed_oracle_bond(accounts(1)),
+            Some(NearToken::from_near(5))
+        );
+
+        contract.slash_oracle(
+            accounts(1),
+            U128(NearToken::from_near(2).as_yoctonear()),
+            "Reported a stale price".to_string(),
+        );
+        assert_eq!(contract.total_bonded, NearToken::from_near(3));
+
+        set_context(accounts(1), ts(10 + BOND_WITHDRAWAL_COOLDOWN_SEC));
+        contract.withdraw_bond();
+        assert_eq!(contract.get_removed_oracle_bond(accounts(1)), None);
+        assert_eq!(contract.total_bonded, NearToken::from_yoctonear(0));
+        let receipts = near_sdk::test_utils::get_created_receipts();
+        assert_eq!(receipts[0].receiver_id, accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "The bond is locked for 604800 seconds after the last report")]
+    fn test_removed_oracle_withdraw_during_cooldown() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        deposit_bond(&mut contract, &accounts(1), NearToken::from_near(5));
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+
+        set_context(accounts(0), ts(20));
+        contract.remove_oracle(accounts(1));
+        set_context(accounts(1), ts(20));
+        contract.withdraw_bond();
+    }
+}
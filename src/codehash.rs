@@ -0,0 +1,68 @@
+use crate::*;
+use near_sdk_macros::NearSchema;
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, NearSchema)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct CodehashApproval {
+    pub approved_at: Timestamp,
+    /// The approval is no longer valid at or after this timestamp. `None` never expires.
+    pub expires_at: Option<Timestamp>,
+}
+
+impl CodehashApproval {
+    pub fn new(expires_at: Option<Timestamp>) -> Self {
+        Self {
+            approved_at: env::block_timestamp(),
+            expires_at,
+        }
+    }
+
+    pub fn is_active(&self, timestamp: Timestamp) -> bool {
+        self.expires_at
+            .is_none_or(|expires_at| timestamp < expires_at)
+    }
+}
+
+/// Returns the codehash in lowercase, panics unless it's a hex encoded sha256 digest as
+/// extracted by `verify_codehash`.
+pub(crate) fn normalize_codehash(codehash: &str) -> String {
+    assert!(
+        codehash.len() == 64 && codehash.chars().all(|c| c.is_ascii_hexdigit()),
+        "Codehash must be 64 hex characters, got {:?}",
+        codehash
+    );
+    codehash.to_ascii_lowercase()
+}
+
+impl Contract {
+    pub fn internal_is_codehash_approved(&self, codehash: &str) -> bool {
+        self.approved_codehashes
+            .get(&codehash.to_ascii_lowercase())
+            .is_some_and(|approval| approval.is_active(env::block_timestamp()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::CODEHASH;
+
+    #[test]
+    fn test_normalize_codehash() {
+        assert_eq!(normalize_codehash(CODEHASH), CODEHASH);
+        assert_eq!(normalize_codehash(&CODEHASH.to_uppercase()), CODEHASH);
+    }
+
+    #[test]
+    #[should_panic(expected = "Codehash must be 64 hex characters")]
+    fn test_normalize_codehash_wrong_length() {
+        normalize_codehash(&CODEHASH[..63]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Codehash must be 64 hex characters")]
+    fn test_normalize_codehash_non_hex() {
+        normalize_codehash(&format!("{}g", &CODEHASH[..63]));
+    }
+}
@@ -1,119 +1,136 @@
 use dcap_qvl::QuoteCollateralV3;
-use near_sdk::require;
 use serde_json::Value;
 use sha2::{Digest as _, Sha256, Sha384};
 
-pub fn get_collateral(raw_quote_collateral: String) -> QuoteCollateralV3 {
-    let quote_collateral: serde_json::Value =
-        serde_json::from_str(&raw_quote_collateral).expect("TCB Info should be valid JSON");
+pub fn get_collateral(raw_quote_collateral: String) -> Result<QuoteCollateralV3, String> {
+    let quote_collateral: Value = serde_json::from_str(&raw_quote_collateral)
+        .map_err(|_| "collateral should be valid JSON".to_string())?;
 
-    let tcb_info_issuer_chain = quote_collateral["tcb_info_issuer_chain"]
-        .as_str()
-        .unwrap()
-        .to_owned();
-    let tcb_info = quote_collateral["tcb_info"].as_str().unwrap().to_owned();
-    let tcb_info_signature =
-        hex::decode(quote_collateral["tcb_info_signature"].as_str().unwrap()).unwrap();
-    let qe_identity_issuer_chain = quote_collateral["qe_identity_issuer_chain"]
-        .as_str()
-        .unwrap()
-        .to_owned();
-    let qe_identity = quote_collateral["qe_identity"].as_str().unwrap().to_owned();
-    let qe_identity_signature =
-        hex::decode(quote_collateral["qe_identity_signature"].as_str().unwrap()).unwrap();
-
-    QuoteCollateralV3 {
-        tcb_info_issuer_chain,
-        tcb_info,
-        tcb_info_signature,
-        qe_identity_issuer_chain,
-        qe_identity,
-        qe_identity_signature,
-    }
+    Ok(QuoteCollateralV3 {
+        tcb_info_issuer_chain: str_field(&quote_collateral, "tcb_info_issuer_chain")?.to_owned(),
+        tcb_info: str_field(&quote_collateral, "tcb_info")?.to_owned(),
+        tcb_info_signature: hex_field(&quote_collateral, "tcb_info_signature")?,
+        qe_identity_issuer_chain: str_field(&quote_collateral, "qe_identity_issuer_chain")?
+            .to_owned(),
+        qe_identity: str_field(&quote_collateral, "qe_identity")?.to_owned(),
+        qe_identity_signature: hex_field(&quote_collateral, "qe_identity_signature")?,
+    })
 }
 
-pub fn verify_codehash(raw_tcb_info: String, rtmr3: String) -> (String, String) {
-    let tcb_info: Value =
-        serde_json::from_str(&raw_tcb_info).expect("TCB Info should be valid JSON");
-    let event_log = tcb_info["event_log"].as_array().unwrap();
+pub fn verify_codehash(raw_tcb_info: String, rtmr3: String) -> Result<(String, String), String> {
+    let tcb_info: Value = serde_json::from_str(&raw_tcb_info)
+        .map_err(|_| "tcb_info should be valid JSON".to_string())?;
+    let event_log = tcb_info["event_log"]
+        .as_array()
+        .ok_or_else(|| "missing event_log".to_string())?;
     // get compose hash from events
-    let expected_compose_hash = event_log
+    let compose_hash_event = event_log
         .iter()
-        .filter(|e| e["event"].as_str().unwrap() == "compose-hash")
-        .next()
-        .unwrap()["digest"]
-        .as_str()
-        .unwrap();
+        .find(|e| e["event"].as_str() == Some("compose-hash"))
+        .ok_or_else(|| "event_log missing compose-hash event".to_string())?;
+    let expected_compose_hash = str_field(compose_hash_event, "digest")?;
 
     // replay the rtmr3 and compose hash
-    let replayed_rtmr3 = replay_rtmr(event_log.to_owned(), 3);
-    let app_compose = tcb_info["app_compose"].as_str().unwrap();
+    let replayed_rtmr3 = replay_rtmr(event_log, 3)?;
+    let app_compose = str_field(&tcb_info, "app_compose")?;
     let replayed_compose_hash: String = replay_app_compose(app_compose);
 
     // compose hash match expected
-    require!(replayed_compose_hash == expected_compose_hash);
+    if replayed_compose_hash != expected_compose_hash {
+        return Err("compose hash doesn't match the compose-hash event".to_string());
+    }
     // event with compose hash matches report rtmr3
-    require!(replayed_rtmr3 == rtmr3);
+    if replayed_rtmr3 != rtmr3 {
+        return Err("replayed rtmr3 doesn't match the report rtmr3".to_string());
+    }
 
-    // extract the codehashes of the shade-agent-api-image and the shade-agent-app-image
+    // extract the codehashes of the shade-agent-api-image and the shade-agent-app-image,
+    // other images (e.g. sidecars) are ignored
     let mut app_compose_string = String::from(app_compose);
     app_compose_string.retain(|c| !c.is_whitespace());
 
-    // will panic if any of the split_once do not occur e.g. malformed yaml and/or missing tag "#shade-agent-api-image"
-    let (_, right) = app_compose_string
-        .split_once("#shade-agent-api-image")
-        .unwrap();
-    let (_, right) = right.split_once("\\nimage:").unwrap();
-    let (left, _) = right.split_once("\\n").unwrap();
-    let (_, right) = left.split_once("@sha256:").unwrap();
-    let (shade_agent_api_image, _) = right.split_at(64);
-
-    // will panic if any of the split_once do not occur e.g. malformed yaml and/or missing tag "#shade-agent-app-image"
-    let (_, right) = app_compose_string
-        .split_once("#shade-agent-app-image")
-        .unwrap();
-    let (_, right) = right.split_once("\\nimage:").unwrap();
-    let (left, _) = right.split_once("\\n").unwrap();
-    let (_, right) = left.split_once("@sha256:").unwrap();
-    let (shade_agent_app_image, _) = right.split_at(64);
-
-    // ensure there are exactly two image declarations in total in the entire app_compose_string
-    let image_declaration_count = app_compose_string.matches("\\nimage:").count();
-    require!(
-        image_declaration_count == 2,
-        "app_compose should contain exactly two image declarations"
-    );
-
-    (
-        shade_agent_api_image.to_owned(),
-        shade_agent_app_image.to_owned(),
-    )
+    let shade_agent_api_image = extract_image_digest(&app_compose_string, "#shade-agent-api-image")?;
+    let shade_agent_app_image = extract_image_digest(&app_compose_string, "#shade-agent-app-image")?;
+
+    Ok((shade_agent_api_image, shade_agent_app_image))
+}
+
+/// Checks that the report data of the quote is the account ID, either as UTF-8 or as hex
+/// encoded UTF-8. Trailing null bytes padding the fixed size field are ignored.
+pub fn verify_report_data(report_data: &[u8], account_id: &str) -> Result<(), String> {
+    let raw = String::from_utf8_lossy(trim_nulls(report_data));
+    if raw == account_id {
+        return Ok(());
+    }
+    let decoded = hex::decode(raw.as_bytes())
+        .ok()
+        .map(|bytes| String::from_utf8_lossy(trim_nulls(&bytes)).into_owned());
+    if decoded.as_deref() == Some(account_id) {
+        return Ok(());
+    }
+    Err(format!(
+        "report_data doesn't match {}: found {:?} as UTF-8 and {:?} as hex",
+        account_id, raw, decoded
+    ))
 }
 
 // helpers
 
-fn replay_rtmr(event_log: Vec<Value>, imr: u8) -> String {
+fn trim_nulls(bytes: &[u8]) -> &[u8] {
+    let len = bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+    &bytes[..len]
+}
+
+fn str_field<'a>(value: &'a Value, field: &str) -> Result<&'a str, String> {
+    value[field]
+        .as_str()
+        .ok_or_else(|| format!("missing {}", field))
+}
+
+fn hex_field(value: &Value, field: &str) -> Result<Vec<u8>, String> {
+    hex::decode(str_field(value, field)?).map_err(|_| format!("{} should be hex encoded", field))
+}
+
+/// Returns the sha256 digest of the image declared right after the given tag, e.g.
+/// `#shade-agent-api-image\nimage:<name>@sha256:<digest>\n`, in the whitespace-free app compose.
+fn extract_image_digest(app_compose: &str, tag: &str) -> Result<String, String> {
+    let (_, right) = app_compose
+        .split_once(tag)
+        .ok_or_else(|| format!("app_compose missing {} tag", tag))?;
+    let (_, right) = right
+        .split_once("\\nimage:")
+        .ok_or_else(|| format!("app_compose missing image declaration after {} tag", tag))?;
+    let image = right.split_once("\\n").map_or(right, |(left, _)| left);
+    let (_, digest) = image
+        .split_once("@sha256:")
+        .ok_or_else(|| format!("{} image should be pinned by sha256 digest", tag))?;
+    digest
+        .get(..64)
+        .filter(|digest| digest.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(|digest| digest.to_owned())
+        .ok_or_else(|| format!("{} image digest should be 64 hex chars", tag))
+}
+
+fn replay_rtmr(event_log: &[Value], imr: u8) -> Result<String, String> {
     let mut digest = [0u8; 48];
 
     // filter by imr
-    let filtered_events = event_log
-        .iter()
-        .filter(|e| e["imr"].as_u64().unwrap() as u8 == imr);
-
-    // hash all digests together
-    for event in filtered_events {
+    for event in event_log {
+        let event_imr = event["imr"]
+            .as_u64()
+            .ok_or_else(|| "event_log event missing imr".to_string())?;
+        if event_imr != u64::from(imr) {
+            continue;
+        }
+        // hash all digests together
         let mut hasher = Sha384::new();
         hasher.update(digest);
-        hasher.update(
-            hex::decode(event["digest"].as_str().unwrap())
-                .unwrap()
-                .as_slice(),
-        );
+        hasher.update(hex_field(event, "digest")?.as_slice());
         digest = hasher.finalize().into();
     }
 
     // return hex encoded digest (rtmr[imr])
-    hex::encode(digest)
+    Ok(hex::encode(digest))
 }
 
 fn replay_app_compose(app_compose: &str) -> String {
@@ -134,3 +151,151 @@ fn replay_app_compose(app_compose: &str) -> String {
     hex::encode(digest)
 }
 
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    const API_DIGEST: &str = "1111111111111111111111111111111111111111111111111111111111111111";
+    const APP_DIGEST: &str = "2222222222222222222222222222222222222222222222222222222222222222";
+
+    /// Returns a consistent tcb_info for the given app compose along with its rtmr3.
+    pub(crate) fn build_tcb_info(app_compose: &str) -> (String, String) {
+        let event_log = vec![serde_json::json!({
+            "imr": 3,
+            "event": "compose-hash",
+            "digest": replay_app_compose(app_compose),
+        })];
+        let rtmr3 = replay_rtmr(&event_log, 3).unwrap();
+        let tcb_info = serde_json::json!({
+            "event_log": event_log,
+            "app_compose": app_compose,
+        });
+        (tcb_info.to_string(), rtmr3)
+    }
+
+    /// Builds an app compose with a service per `(tag, image)` pair. An empty tag omits the
+    /// tag comment.
+    pub(crate) fn build_app_compose(images: &[(&str, &str)]) -> String {
+        let services: String = images
+            .iter()
+            .enumerate()
+            .map(|(i, (tag, image))| {
+                format!("  service{}:\\n    {}\\n    image: {}\\n", i, tag, image)
+            })
+            .collect();
+        format!("{{\"docker_compose_file\":\"services:\\n{}\"}}", services)
+    }
+
+    #[test]
+    fn test_get_collateral_errors() {
+        assert_eq!(
+            get_collateral(r#"{"tcb_info_issuer_chain": "chain""#.to_string()).err(),
+            Some("collateral should be valid JSON".to_string())
+        );
+        assert_eq!(
+            get_collateral(r#"{"tcb_info_issuer_chain": "chain", "tcb_info": "{}"}"#.to_string())
+                .err(),
+            Some("missing tcb_info_signature".to_string())
+        );
+        assert_eq!(
+            get_collateral(
+                r#"{"tcb_info_issuer_chain": "chain", "tcb_info": "{}", "tcb_info_signature": "zz"}"#
+                    .to_string()
+            )
+            .err(),
+            Some("tcb_info_signature should be hex encoded".to_string())
+        );
+    }
+
+    /// Returns the 64 bytes report data field holding the given bytes, padded with nulls.
+    fn padded_report_data(bytes: &[u8]) -> [u8; 64] {
+        let mut report_data = [0u8; 64];
+        report_data[..bytes.len()].copy_from_slice(bytes);
+        report_data
+    }
+
+    #[test]
+    fn test_verify_report_data() {
+        let hex_account_id = hex::encode("alice.near");
+        for report_data in [
+            b"alice.near".to_vec(),
+            padded_report_data(b"alice.near").to_vec(),
+            padded_report_data(hex_account_id.as_bytes()).to_vec(),
+        ] {
+            assert_eq!(verify_report_data(&report_data, "alice.near"), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_verify_report_data_mismatch() {
+        assert_eq!(
+            verify_report_data(&padded_report_data(b"bob.near"), "alice.near"),
+            Err("report_data doesn't match alice.near: found \"bob.near\" as UTF-8 and None as hex"
+                .to_string())
+        );
+    }
+
+    #[test]
+    fn test_verify_codehash() {
+        let app_compose = build_app_compose(&[
+            ("#shade-agent-api-image", format!("api@sha256:{}", API_DIGEST).as_str()),
+            ("#shade-agent-app-image", format!("app@sha256:{}", APP_DIGEST).as_str()),
+        ]);
+        let (tcb_info, rtmr3) = build_tcb_info(&app_compose);
+        assert_eq!(
+            verify_codehash(tcb_info, rtmr3),
+            Ok((API_DIGEST.to_string(), APP_DIGEST.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_verify_codehash_ignores_extra_images() {
+        let app_compose = build_app_compose(&[
+            ("", "proxy@sha256:3333333333333333333333333333333333333333333333333333333333333333"),
+            ("#shade-agent-app-image", format!("app@sha256:{}", APP_DIGEST).as_str()),
+            ("#shade-agent-api-image", format!("api@sha256:{}", API_DIGEST).as_str()),
+        ]);
+        let (tcb_info, rtmr3) = build_tcb_info(&app_compose);
+        assert_eq!(
+            verify_codehash(tcb_info, rtmr3),
+            Ok((API_DIGEST.to_string(), APP_DIGEST.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_verify_codehash_errors() {
+        assert_eq!(
+            verify_codehash(r#"{"event_log": ["#.to_string(), String::new()),
+            Err("tcb_info should be valid JSON".to_string())
+        );
+        assert_eq!(
+            verify_codehash(r#"{"event_log": []}"#.to_string(), String::new()),
+            Err("event_log missing compose-hash event".to_string())
+        );
+
+        let app_compose = build_app_compose(&[(
+            "#shade-agent-app-image",
+            format!("app@sha256:{}", APP_DIGEST).as_str(),
+        )]);
+        let (tcb_info, rtmr3) = build_tcb_info(&app_compose);
+        assert_eq!(
+            verify_codehash(tcb_info.clone(), "00".to_string()),
+            Err("replayed rtmr3 doesn't match the report rtmr3".to_string())
+        );
+        assert_eq!(
+            verify_codehash(tcb_info, rtmr3),
+            Err("app_compose missing #shade-agent-api-image tag".to_string())
+        );
+
+        let app_compose = build_app_compose(&[
+            ("#shade-agent-api-image", "api@sha256:1234"),
+            ("#shade-agent-app-image", format!("app@sha256:{}", APP_DIGEST).as_str()),
+        ]);
+        let (tcb_info, rtmr3) = build_tcb_info(&app_compose);
+        assert_eq!(
+            verify_codehash(tcb_info, rtmr3),
+            Err("#shade-agent-api-image image digest should be 64 hex chars".to_string())
+        );
+    }
+}
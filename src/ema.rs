@@ -9,35 +9,64 @@ pub struct AssetEma {
     pub period_sec: DurationSec,
     pub timestamp: Timestamp,
     pub price: Option<Price>,
+    /// Fixed smoothing factor applied on every update, in basis points. When `None`, the
+    /// smoothing factor is derived from `period_sec` and the time since the last update.
+    pub alpha_bps: Option<u16>,
 }
 
 impl AssetEma {
-    pub fn new(period_sec: DurationSec) -> AssetEma {
+    pub fn new(period_sec: DurationSec, alpha_bps: Option<u16>) -> AssetEma {
+        assert!(period_sec > 0, "EMA period must be positive");
+        if let Some(alpha_bps) = alpha_bps {
+            assert!(
+                alpha_bps > 0 && alpha_bps <= 10000,
+                "alpha_bps must be within (0, 10000]"
+            );
+        }
         Self {
             period_sec,
             timestamp: 0,
             price: None,
+            alpha_bps,
         }
     }
 
+    /// Clears the value, so the next recompute seeds it from the median. Keeps the config.
+    pub fn reset(&mut self) {
+        self.timestamp = 0;
+        self.price = None;
+    }
+
+    /// A fixed smoothing factor is applied at most once per timestamp, so that several
+    /// reports within the same block don't compound it.
     pub fn recompute(&mut self, median_price: Price, timestamp: Timestamp) {
         if let Some(current) = self.price.as_mut() {
-            let time_diff = timestamp - self.timestamp;
-            // Based on https://stackoverflow.com/questions/1023860/exponential-moving-average-sampled-at-varying-times
-            let alpha =
-                1.0f64 - (-2.0f64 * time_diff as f64 / to_nano(self.period_sec) as f64).exp();
-            let mut current_f64 = current.multiplier as f64;
+            let alpha = if let Some(alpha_bps) = self.alpha_bps {
+                if timestamp <= self.timestamp {
+                    return;
+                }
+                f64::from(alpha_bps) / 10000f64
+            } else {
+                let time_diff = timestamp - self.timestamp;
+                // Based on https://stackoverflow.com/questions/1023860/exponential-moving-average-sampled-at-varying-times
+                1.0f64 - (-2.0f64 * time_diff as f64 / to_nano(self.period_sec) as f64).exp()
+            };
+            let mut current_f64 = signed_multiplier(current);
             current_f64 *= 10f64.powi(median_price.decimals as i32 - current.decimals as i32);
-            current_f64 += alpha * (median_price.multiplier as f64 - current_f64);
+            current_f64 += alpha * (signed_multiplier(&median_price) - current_f64);
+            let negative = current_f64 < 0f64;
+            let current_f64 = current_f64.abs();
             if current_f64 <= MAX_F64_FOR_PRECISE_MULTIPLIER {
                 *current = Price {
                     multiplier: (current_f64 * 1e4).round() as u128,
                     decimals: median_price.decimals + 4,
+                    negative,
                 }
             } else {
                 *current = Price {
                     multiplier: current_f64.round() as u128,
                     decimals: median_price.decimals,
+                    negative,
                 }
             }
         } else {
@@ -47,6 +76,46 @@ impl AssetEma {
     }
 }
 
+fn signed_multiplier(price: &Price) -> f64 {
+    if price.negative {
+        -(price.multiplier as f64)
+    } else {
+        price.multiplier as f64
+    }
+}
+
+#[near]
+impl Contract {
+    /// Recomputes the EMAs of the given assets against their current fresh median at the
+    /// current block timestamp, so EMAs stay fresh even without new reports. It's a no-op for
+    /// assets without a fresh median, and for EMAs already updated at the current block
+    /// timestamp.
+    pub fn touch_emas(&mut self, asset_ids: Vec<AssetId>) {
+        let timestamp = env::block_timestamp();
+        let min_num_recent_reports = self.internal_min_num_recent_reports();
+        let inactive_oracle_ids = self.internal_inactive_oracle_ids();
+        for asset_id in asset_ids {
+            let mut asset = self.internal_get_asset(&asset_id).expect("Unknown asset");
+            if asset.emas.iter().all(|ema| ema.timestamp >= timestamp) {
+                continue;
+            }
+            let recency_duration_sec =
+                asset.recency_duration_sec.unwrap_or(self.recency_duration_sec);
+            let timestamp_cut = timestamp.saturating_sub(to_nano(recency_duration_sec));
+            if let Some(median_price) = asset.active_median_price(
+                timestamp_cut,
+                asset.min_num_recent_reports(min_num_recent_reports),
+                &inactive_oracle_ids,
+            ) {
+                for ema in asset.emas.iter_mut().filter(|ema| ema.timestamp < timestamp) {
+                    ema.recompute(median_price, timestamp);
+                }
+                self.internal_set_asset(&asset_id, asset);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{to_nano, AssetEma, Price};
@@ -63,6 +132,7 @@ mod tests {
         Price {
             multiplier,
             decimals: BASE_DECIMALS,
+            negative: false,
         }
     }
 
@@ -72,6 +142,7 @@ mod tests {
             period_sec: 60000,
             timestamp: ts(0),
             price: None,
+            alpha_bps: None,
         };
         let timestamp = ts(10);
         let price = mp(100000);
@@ -80,6 +151,42 @@ mod tests {
         assert_eq!(ema.price, Some(price));
     }
 
+    #[test]
+    pub fn test_ema_alpha_bps() {
+        let mut fast = AssetEma::new(600, Some(5000));
+        let mut slow = AssetEma::new(600, Some(1000));
+        for ema in [&mut fast, &mut slow] {
+            ema.recompute(mp(100_0000), ts(0));
+            for i in 1..=3 {
+                ema.recompute(mp(200_0000), ts(60 * i));
+            }
+        }
+        let value = |ema: &AssetEma| {
+            let price = ema.price.unwrap();
+            (price.multiplier as f64) / 10f64.powi(price.decimals as i32 - BASE_DECIMALS as i32)
+        };
+        // 100 -> 150 -> 175 -> 187.5 with alpha 0.5, 100 -> 110 -> 119 -> 127.1 with alpha 0.1.
+        assert_relative_eq!(value(&fast), 187_5000f64, epsilon = 1.0);
+        assert_relative_eq!(value(&slow), 127_1000f64, epsilon = 1.0);
+    }
+
+    #[test]
+    pub fn test_ema_alpha_bps_same_timestamp() {
+        let mut ema = AssetEma::new(600, Some(5000));
+        ema.recompute(mp(100_0000), ts(0));
+        ema.recompute(mp(200_0000), ts(60));
+        let price = ema.price;
+        ema.recompute(mp(200_0000), ts(60));
+        assert_eq!(ema.price, price);
+        assert_eq!(ema.timestamp, ts(60));
+    }
+
+    #[test]
+    #[should_panic(expected = "alpha_bps must be within (0, 10000]")]
+    pub fn test_ema_zero_alpha_bps() {
+        AssetEma::new(600, Some(0));
+    }
+
     #[test]
     pub fn test_ema_period() {
         let price_multipliers = vec![
@@ -98,6 +205,7 @@ mod tests {
             period_sec,
             timestamp: ts(0),
             price: None,
+            alpha_bps: None,
         };
         for (i, (multiplier, expected_ema)) in
             price_multipliers.into_iter().zip(expected_emas).enumerate()
@@ -0,0 +1,38 @@
+//! Error codes of the reporting path. Panic messages of `report_prices`,
+//! `report_prices_signed` and the checks they run start with one of these codes followed by
+//! `: `, e.g. `E_NOT_ORACLE: Not an oracle`, so relayers can branch on the failure reason of a
+//! transaction outcome without matching the full message.
+
+/// The report has no prices.
+pub const E_EMPTY_REPORT: &str = "E_EMPTY_REPORT";
+/// The report has more prices than `max_report_batch`.
+pub const E_BATCH_TOO_LARGE: &str = "E_BATCH_TOO_LARGE";
+/// A strict report has a price of an unknown asset.
+pub const E_UNKNOWN_ASSET: &str = "E_UNKNOWN_ASSET";
+/// The reporter is not a registered oracle.
+pub const E_NOT_ORACLE: &str = "E_NOT_ORACLE";
+/// The oracle has no codehash or its codehash is not approved.
+pub const E_CODEHASH: &str = "E_CODEHASH";
+/// The oracle has no checksum or its checksum is not approved, see
+/// `require_approved_checksum`.
+pub const E_CHECKSUM: &str = "E_CHECKSUM";
+/// The oracle is scheduled for removal.
+pub const E_ORACLE_REMOVAL: &str = "E_ORACLE_REMOVAL";
+/// The report isn't later than the last report of the oracle.
+pub const E_TIMESTAMP: &str = "E_TIMESTAMP";
+/// The oracle reports more often than `min_report_interval_sec`.
+pub const E_REPORT_INTERVAL: &str = "E_REPORT_INTERVAL";
+/// The price is not acceptable for the asset: too many decimals, negative or zero.
+pub const E_INVALID_PRICE: &str = "E_INVALID_PRICE";
+/// The price fails the asset's `VALIDATE_BOUNDS` or `VALIDATE_DECIMALS` check.
+pub const E_VALIDATION: &str = "E_VALIDATION";
+/// A strict report has a price outside of the asset's sanity bounds.
+pub const E_PRICE_BOUNDS: &str = "E_PRICE_BOUNDS";
+/// The price fails the asset's `VALIDATE_DEVIATION` check.
+pub const E_DEVIATION: &str = "E_DEVIATION";
+/// The observation time of the price is outside of the accepted window.
+pub const E_OBSERVED_AT: &str = "E_OBSERVED_AT";
+/// A relayed report has no valid signature of the oracle's key.
+pub const E_SIGNATURE: &str = "E_SIGNATURE";
+/// A relayed report reuses a nonce of the oracle.
+pub const E_NONCE: &str = "E_NONCE";
@@ -0,0 +1,178 @@
+use crate::*;
+use near_sdk_macros::NearSchema;
+
+/// Upper bound on the number of snapshots stored per asset.
+pub const MAX_HISTORY_SAMPLES: u32 = 256;
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Copy, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriceSnapshot {
+    pub timestamp: Timestamp,
+    pub price: Price,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetHistory {
+    pub max_samples: u32,
+    /// Median snapshots ordered by timestamp, oldest first.
+    pub snapshots: Vec<PriceSnapshot>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, NearSchema)]
+pub enum VAssetHistory {
+    Current(AssetHistory),
+}
+
+impl From<VAssetHistory> for AssetHistory {
+    fn from(v: VAssetHistory) -> Self {
+        match v {
+            VAssetHistory::Current(history) => history,
+        }
+    }
+}
+
+impl From<AssetHistory> for VAssetHistory {
+    fn from(c: AssetHistory) -> Self {
+        VAssetHistory::Current(c)
+    }
+}
+
+impl AssetHistory {
+    pub fn new(max_samples: u32) -> Self {
+        Self {
+            max_samples,
+            snapshots: Vec::new(),
+        }
+    }
+
+    /// Appends a snapshot, replacing a snapshot from the same block and evicting the oldest
+    /// ones beyond `max_samples`.
+    pub fn record(&mut self, snapshot: PriceSnapshot) {
+        if let Some(last) = self.snapshots.last_mut() {
+            if last.timestamp == snapshot.timestamp {
+                *last = snapshot;
+                return;
+            }
+        }
+        self.snapshots.push(snapshot);
+        self.truncate();
+    }
+
+    fn truncate(&mut self) {
+        let max_samples = self.max_samples as usize;
+        if self.snapshots.len() > max_samples {
+            self.snapshots.drain(..self.snapshots.len() - max_samples);
+        }
+    }
+
+    /// Returns the most recent snapshot at or before the given timestamp.
+    pub fn price_at(&self, timestamp: Timestamp) -> Option<&PriceSnapshot> {
+        let index = self
+            .snapshots
+            .partition_point(|snapshot| snapshot.timestamp <= timestamp);
+        index.checked_sub(1).and_then(|index| self.snapshots.get(index))
+    }
+}
+
+#[near]
+impl Contract {
+    /// Starts recording the median price of the asset on every report, keeping at most
+    /// `max_samples` (up to `MAX_HISTORY_SAMPLES`) latest snapshots. Calling it again for an
+    /// asset with history changes the cap and keeps the existing snapshots.
+    pub fn enable_asset_history(&mut self, asset_id: AssetId, max_samples: u32) {
+        self.assert_admin();
+        assert!(self.assets.contains_key(&asset_id), "Missing an asset");
+        assert!(
+            max_samples > 0 && max_samples <= MAX_HISTORY_SAMPLES,
+            "max_samples must be between 1 and {}",
+            MAX_HISTORY_SAMPLES
+        );
+        let mut history = self
+            .asset_histories
+            .remove(&asset_id)
+            .map_or_else(|| AssetHistory::new(max_samples), |v| v.into());
+        history.max_samples = max_samples;
+        history.truncate();
+        self.asset_histories.insert(asset_id, history.into());
+    }
+
+    /// Stops recording the asset's history and removes the stored snapshots.
+    pub fn disable_asset_history(&mut self, asset_id: AssetId) {
+        self.assert_admin();
+        assert!(
+            self.asset_histories.remove(&asset_id).is_some(),
+            "History is not enabled for this asset"
+        );
+    }
+
+    /// Returns the most recent median snapshot of the asset at or before the given timestamp.
+    pub fn get_price_at(&self, asset_id: AssetId, timestamp: Timestamp) -> Option<AssetOptionalPrice> {
+        let snapshot = *self.internal_get_asset_history(&asset_id)?.price_at(timestamp)?;
+        Some(AssetOptionalPrice {
+            asset_id,
+            price: Some(snapshot.price),
+            status: None,
+            source_asset_id: None,
+        })
+    }
+}
+
+impl Contract {
+    pub fn internal_get_asset_history(&self, asset_id: &AssetId) -> Option<AssetHistory> {
+        self.asset_histories.get(asset_id).map(|v| match v {
+            VAssetHistory::Current(history) => history.clone(),
+        })
+    }
+
+    pub fn internal_record_price_snapshot(
+        &mut self,
+        asset_id: &AssetId,
+        price: Price,
+        timestamp: Timestamp,
+    ) {
+        if let Some(mut history) = self.internal_get_asset_history(asset_id) {
+            history.record(PriceSnapshot { timestamp, price });
+            self.asset_histories.insert(asset_id.clone(), history.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::*;
+    use crate::*;
+    use near_sdk::test_utils::accounts;
+
+    fn price_at(contract: &Contract, timestamp: Timestamp) -> Option<Price> {
+        contract
+            .get_price_at("wrap.near".to_string(), timestamp)
+            .and_then(|p| p.price)
+    }
+
+    #[test]
+    fn test_price_history() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        contract.enable_asset_history("wrap.near".to_string(), 3);
+        for (sec, multiplier) in [(10, 100), (20, 110), (30, 120), (40, 130)] {
+            report(&mut contract, &accounts(1), ts(sec), &[("wrap.near", price(multiplier, 0))]);
+        }
+
+        // The oldest snapshot was evicted by the cap.
+        assert_eq!(price_at(&contract, ts(15)), None);
+        assert_eq!(price_at(&contract, ts(20)), Some(price(110, 0)));
+        assert_eq!(price_at(&contract, ts(35)), Some(price(120, 0)));
+        assert_eq!(price_at(&contract, ts(1000)), Some(price(130, 0)));
+    }
+
+    #[test]
+    fn test_price_history_disabled() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(100, 0))]);
+        assert_eq!(price_at(&contract, ts(10)), None);
+    }
+}
@@ -1,16 +1,98 @@
 use crate::*;
 use near_sdk_macros::NearSchema;
 
+/// Root state of the baseline contract, read by `migrate_state`.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ContractV0 {
+    pub oracles: UnorderedMap<AccountId, VOracle>,
+    pub assets: UnorderedMap<AssetId, VAsset>,
+    pub recency_duration_sec: DurationSec,
+    pub owner_id: AccountId,
+    pub near_claim_amount: NearToken,
+    pub approved_codehashes: IterableSet<String>,
+    pub worker_by_account_id: IterableMap<AccountId, Worker>,
+}
+
+/// Stored layout of `Price` before it could be negative.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, NearSchema)]
+pub struct PriceV0 {
+    pub multiplier: u128,
+    pub decimals: u8,
+}
+
+impl From<PriceV0> for Price {
+    fn from(v: PriceV0) -> Self {
+        Price {
+            multiplier: v.multiplier,
+            decimals: v.decimals,
+            negative: false,
+        }
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, NearSchema)]
+pub struct ReportV0 {
+    pub oracle_id: AccountId,
+    pub timestamp: Timestamp,
+    pub price: PriceV0,
+}
+
+impl From<ReportV0> for Report {
+    fn from(v: ReportV0) -> Self {
+        Report {
+            oracle_id: v.oracle_id,
+            timestamp: v.timestamp,
+            price: v.price.into(),
+        }
+    }
+}
+
+/// Stored layout of `AssetEma` before the fixed smoothing factor.
+#[derive(BorshSerialize, BorshDeserialize, Clone, NearSchema)]
+pub struct AssetEmaV0 {
+    pub period_sec: DurationSec,
+    pub timestamp: Timestamp,
+    pub price: Option<PriceV0>,
+}
+
+impl From<AssetEmaV0> for AssetEma {
+    fn from(v: AssetEmaV0) -> Self {
+        AssetEma {
+            period_sec: v.period_sec,
+            timestamp: v.timestamp,
+            price: v.price.map(|price| price.into()),
+            alpha_bps: None,
+        }
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Clone, NearSchema)]
 pub struct AssetV0 {
-    pub reports: Vec<Report>,
+    pub reports: Vec<ReportV0>,
 }
 
 impl From<AssetV0> for Asset {
     fn from(v: AssetV0) -> Self {
         Asset {
-            reports: v.reports,
-            emas: vec![],
+            reports: v.reports.into_iter().map(|rp| rp.into()).collect(),
+            ..Asset::new()
+        }
+    }
+}
+
+/// Stored layout of `Asset` before the market sessions.
+#[derive(BorshSerialize, BorshDeserialize, Clone, NearSchema)]
+pub struct AssetV1 {
+    pub reports: Vec<ReportV0>,
+    pub emas: Vec<AssetEmaV0>,
+}
+
+impl From<AssetV1> for Asset {
+    fn from(v: AssetV1) -> Self {
+        Asset {
+            reports: v.reports.into_iter().map(|rp| rp.into()).collect(),
+            emas: v.emas.into_iter().map(|ema| ema.into()).collect(),
+            ..Asset::new()
         }
     }
 }
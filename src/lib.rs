@@ -1,27 +1,43 @@
+// `store::UnorderedMap` is deprecated, but the oracles and assets keep its storage layout.
+#![allow(deprecated)]
+
 mod asset;
+mod audit;
+mod bond;
+mod codehash;
 mod collateral;
 mod ema;
+mod errors;
+mod history;
 mod legacy;
 mod oracle;
 mod owner;
+mod signed_report;
 mod upgrade;
 mod utils;
 
 pub use crate::asset::*;
+pub use crate::audit::*;
+pub use crate::bond::*;
+pub use crate::codehash::*;
 pub use crate::ema::*;
+pub use crate::errors::*;
+pub use crate::history::*;
 use crate::legacy::*;
 pub use crate::oracle::*;
+pub use crate::signed_report::*;
 pub use crate::utils::*;
 
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::store::{UnorderedMap, IterableSet, IterableMap};
+use near_sdk::store::{UnorderedMap, IterableMap, IterableSet, LookupMap, Vector};
 use near_sdk::json_types::U128;
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
     assert_one_yocto, env, ext_contract, log, near, require, AccountId, NearToken, Gas, BorshStorageKey,
-    Duration, Promise, Timestamp,
+    Promise, PromiseResult, PublicKey, Timestamp,
 };
 use near_sdk_macros::NearSchema;
+use std::collections::HashSet;
 use hex::{decode, encode};
 use dcap_qvl::verify;
 
@@ -29,9 +45,27 @@ const NO_DEPOSIT: NearToken = NearToken::from_yoctonear(0);
 
 const GAS_FOR_PROMISE: Gas = Gas::from_tgas(10);
 
-const NEAR_CLAIM_DURATION: Duration = 24 * 60 * 60 * 10u64.pow(9);
+const MAX_GAS_FOR_PROMISE: Gas = Gas::from_tgas(100);
+
+const GAS_FOR_ON_ORACLE_CALL_COMPLETE: Gas = Gas::from_tgas(5);
+
+/// Fees of creating the `on_oracle_call_complete` receipt and the data receipt it waits on.
+const GAS_FOR_CALLBACK_RECEIPT: Gas = Gas::from_tgas(15);
+
+const DEFAULT_NEAR_CLAIM_COOLDOWN_SEC: DurationSec = 24 * 60 * 60;
 // This is a safety margin in NEAR for to cover potential extra storage.
-const SAFETY_MARGIN_NEAR_CLAIM: NearToken = NearToken::from_near(1);
+const DEFAULT_CLAIM_SAFETY_MARGIN: NearToken = NearToken::from_near(1);
+
+const MAX_ASSETS_WITH_PRICES_LIMIT: u64 = 50;
+
+const DEFAULT_MAX_REPORT_BATCH: u32 = 200;
+
+const DEFAULT_MAX_PRICE_DATA_ASSETS: u32 = 100;
+
+const DEFAULT_MAX_ORACLE_CALL_MSG_LEN: u32 = 8192;
+
+/// Maximum number of fallbacks followed when an asset has no fresh price.
+const MAX_FALLBACK_DEPTH: usize = 3;
 
 pub type DurationSec = u32;
 
@@ -40,6 +74,17 @@ enum StorageKey {
     Oracles,
     Assets,
     ApprovedCodehashes,
+    AssetHistories,
+    OracleDeactivations,
+    AssetAliases,
+    Admins,
+    ApprovedChecksums,
+    OracleNonces,
+    AssetFallbacks,
+    OwnerActions,
+    OracleIdsByIndex,
+    OracleIndices,
+    RemovedOracleBonds,
 }
 
 #[near(serializers = [json, borsh])]
@@ -61,9 +106,79 @@ pub struct Contract {
 
     pub near_claim_amount: NearToken,
 
-    pub approved_codehashes: IterableSet<String>,
+    pub approved_codehashes: IterableMap<String, CodehashApproval>,
 
     pub worker_by_account_id: IterableMap<AccountId, Worker>,
+
+    /// Maximum number of registered oracles. `None` means unlimited.
+    pub max_oracles: Option<u32>,
+
+    /// Bounded median price history of assets that opted in via `enable_asset_history`.
+    pub asset_histories: LookupMap<AssetId, VAssetHistory>,
+
+    /// Oracles scheduled for removal with the timestamp when they stop counting toward quorum.
+    pub oracle_deactivations: IterableMap<AccountId, Timestamp>,
+
+    /// Asset IDs redirected to another asset ID when serving prices, e.g. after a rename.
+    pub asset_aliases: IterableMap<AssetId, AssetId>,
+
+    /// Minimum time between two reports of the same oracle. 0 disables the limit.
+    pub min_report_interval_sec: DurationSec,
+
+    /// Account proposed by the owner to take over the ownership via `accept_ownership`.
+    pub pending_owner: Option<AccountId>,
+
+    /// Accounts allowed to call operational methods besides the owner.
+    pub admins: IterableSet<AccountId>,
+
+    /// When set, `report_prices` drops the reports of updated assets older than this.
+    pub report_prune_threshold_sec: Option<DurationSec>,
+
+    /// Minimum time between two NEAR claims of the same oracle.
+    pub near_claim_cooldown_sec: DurationSec,
+
+    /// Checksums of the agent builds allowed to report when `require_approved_checksum` is on.
+    pub approved_checksums: IterableSet<String>,
+
+    /// When true, reports also require the checksum registered by `register_agent` to be
+    /// approved.
+    pub require_approved_checksum: bool,
+
+    /// Last nonce used by each oracle in `report_prices_signed`.
+    pub oracle_nonces: LookupMap<AccountId, u64>,
+
+    /// Maximum number of prices in a single report.
+    pub max_report_batch: u32,
+
+    /// Assets serving the price of another asset in `get_price_data` when it's stale.
+    pub asset_fallbacks: IterableMap<AssetId, AssetId>,
+
+    /// Maximum length in bytes of the `msg` forwarded by `oracle_call`.
+    pub max_oracle_call_msg_len: u32,
+
+    /// Sum of the oracle bonds, which are excluded from the balance available for NEAR claims.
+    pub total_bonded: NearToken,
+    /// Bonds of the removed oracles, withdrawable with `withdraw_bond` after the cooldown.
+    pub removed_oracle_bonds: LookupMap<AccountId, RemovedOracleBond>,
+
+    /// Audit log of the sensitive owner and admin operations, keyed by sequence number.
+    pub owner_actions: LookupMap<u64, OwnerAction>,
+    pub num_owner_actions: u64,
+
+    /// Minimum number of fresh reports required to serve a price, regardless of the number of
+    /// oracles.
+    pub global_min_reports: u32,
+
+    /// Oracle IDs interned in the stored reports, by index and the other way around. See
+    /// `internal_oracle_index`.
+    pub oracle_ids_by_index: Vector<AccountId>,
+    pub oracle_indices: LookupMap<AccountId, u32>,
+
+    /// Balance kept on top of a NEAR claim to cover potential extra storage.
+    pub claim_safety_margin: NearToken,
+
+    /// Maximum number of assets `get_price_data` returns when no asset IDs are given.
+    pub max_price_data_assets: u32,
 }
 
 #[derive(Serialize, Deserialize, NearSchema)]
@@ -75,6 +190,58 @@ pub struct PriceData {
     pub prices: Vec<AssetOptionalPrice>,
 }
 
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriceDataMulti {
+    pub timestamp: Timestamp,
+
+    pub prices: Vec<AssetQueryPrice>,
+}
+
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriceDataWithSpread {
+    pub timestamp: Timestamp,
+    pub recency_duration_sec: DurationSec,
+
+    pub prices: Vec<AssetPriceSpread>,
+}
+
+/// Attestation extracted by the checks of `register_agent`.
+#[derive(Serialize, Deserialize, NearSchema)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct AgentAttestation {
+    /// Hex encoded report data of the quote, which `register_agent` matches against the caller.
+    pub report_data: String,
+    pub api_codehash: String,
+    pub app_codehash: String,
+    pub api_codehash_approved: bool,
+    pub app_codehash_approved: bool,
+}
+
+/// Outcome of `verify_agent`. Exactly one of `attestation` and `error` is set.
+#[derive(Serialize, Deserialize, NearSchema)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct AgentVerificationResult {
+    pub attestation: Option<AgentAttestation>,
+    /// Why the quote or tcb_info didn't verify.
+    pub error: Option<String>,
+}
+
+/// Overview of the contract configuration for monitoring, see `get_status`.
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContractStatus {
+    pub owner_id: AccountId,
+    pub num_oracles: u32,
+    pub num_assets: u32,
+    pub recency_duration_sec: DurationSec,
+    pub near_claim_amount: U128,
+    pub version: String,
+}
+
 #[ext_contract]
 pub trait ExtPriceReceiver {
     fn oracle_on_call(&mut self, sender_id: AccountId, data: PriceData, msg: String);
@@ -95,8 +262,32 @@ impl Contract {
             recency_duration_sec: 3600,
             owner_id,
             near_claim_amount: NearToken::from_yoctonear(1000000000000000000000000),
-            approved_codehashes: IterableSet::new(b"a"),
+            approved_codehashes: IterableMap::new(StorageKey::ApprovedCodehashes),
             worker_by_account_id: IterableMap::new(b"b"),
+            max_oracles: None,
+            asset_histories: LookupMap::new(StorageKey::AssetHistories),
+            oracle_deactivations: IterableMap::new(StorageKey::OracleDeactivations),
+            asset_aliases: IterableMap::new(StorageKey::AssetAliases),
+            min_report_interval_sec: 0,
+            pending_owner: None,
+            admins: IterableSet::new(StorageKey::Admins),
+            report_prune_threshold_sec: None,
+            near_claim_cooldown_sec: DEFAULT_NEAR_CLAIM_COOLDOWN_SEC,
+            approved_checksums: IterableSet::new(StorageKey::ApprovedChecksums),
+            require_approved_checksum: false,
+            oracle_nonces: LookupMap::new(StorageKey::OracleNonces),
+            max_report_batch: DEFAULT_MAX_REPORT_BATCH,
+            asset_fallbacks: IterableMap::new(StorageKey::AssetFallbacks),
+            max_oracle_call_msg_len: DEFAULT_MAX_ORACLE_CALL_MSG_LEN,
+            total_bonded: NearToken::from_yoctonear(0),
+            removed_oracle_bonds: LookupMap::new(StorageKey::RemovedOracleBonds),
+            owner_actions: LookupMap::new(StorageKey::OwnerActions),
+            num_owner_actions: 0,
+            global_min_reports: 0,
+            oracle_ids_by_index: Vector::new(StorageKey::OracleIdsByIndex),
+            oracle_indices: LookupMap::new(StorageKey::OracleIndices),
+            claim_safety_margin: DEFAULT_CLAIM_SAFETY_MARGIN,
+            max_price_data_assets: DEFAULT_MAX_PRICE_DATA_ASSETS,
         }
     }
 
@@ -123,56 +314,636 @@ impl Contract {
         unordered_map_pagination(&self.oracles, from_index, limit)
     }
 
+    /// Returns oracles whose last report is older than `threshold_sec` seconds, including
+    /// oracles that have never reported.
+    pub fn get_stale_oracles(&self, threshold_sec: DurationSec) -> Vec<AccountId> {
+        let timestamp_cut = env::block_timestamp().saturating_sub(to_nano(threshold_sec));
+        self.oracles
+            .iter()
+            .filter(|(_, v)| {
+                let oracle: Oracle = (*v).into();
+                oracle.last_report == 0 || oracle.last_report < timestamp_cut
+            })
+            .map(|(account_id, _)| account_id.clone())
+            .collect()
+    }
+
+    /// Returns the contract configuration and the number of oracles and assets in one call.
+    pub fn get_status(&self) -> ContractStatus {
+        ContractStatus {
+            owner_id: self.owner_id.clone(),
+            num_oracles: self.oracles.len(),
+            num_assets: self.assets.len(),
+            recency_duration_sec: self.recency_duration_sec,
+            near_claim_amount: U128(self.near_claim_amount.as_yoctonear()),
+            version: self.get_version(),
+        }
+    }
+
     pub fn get_assets(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<(AssetId, Asset)> {
-        unordered_map_pagination(&self.assets, from_index, limit)
+        self.internal_get_assets_page(|_| true, from_index, limit)
+    }
+
+    /// Returns a page of the assets whose IDs start with the given prefix.
+    pub fn get_assets_by_prefix(
+        &self,
+        prefix: String,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<(AssetId, Asset)> {
+        self.internal_get_assets_page(|asset_id| asset_id.starts_with(&prefix), from_index, limit)
     }
 
     pub fn get_asset(&self, asset_id: AssetId) -> Option<Asset> {
         self.internal_get_asset(&asset_id)
     }
 
-    pub fn get_price_data(&self, asset_ids: Option<Vec<AssetId>>) -> PriceData {
-        let asset_ids = asset_ids.unwrap_or_else(|| self.assets.keys().cloned().collect());
+    /// Same as `get_assets`, but also returns the current median price of each asset. The page
+    /// size is capped, since every price is computed over the reports of the asset.
+    pub fn get_assets_with_prices(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<(AssetId, Asset, Option<Price>)> {
+        let limit = limit
+            .unwrap_or(MAX_ASSETS_WITH_PRICES_LIMIT)
+            .min(MAX_ASSETS_WITH_PRICES_LIMIT);
         let timestamp = env::block_timestamp();
-        let timestamp_cut = timestamp.saturating_sub(to_nano(self.recency_duration_sec));
-        let min_num_recent_reports = std::cmp::max(1, (self.oracles.len() + 1) / 2) as usize;
+        let min_num_recent_reports = self.internal_min_num_recent_reports();
+        let inactive_oracle_ids = self.internal_inactive_oracle_ids();
 
-        PriceData {
+        self.internal_get_assets_page(|_| true, from_index, Some(limit))
+            .into_iter()
+            .map(|(asset_id, asset): (AssetId, Asset)| {
+                let timestamp_cut = self.internal_timestamp_cut(&asset_id, timestamp);
+                let price = self
+                    .internal_get_price(
+                        asset_id.clone(),
+                        timestamp_cut,
+                        min_num_recent_reports,
+                        &inactive_oracle_ids,
+                    )
+                    .price;
+                (asset_id, asset, price)
+            })
+            .collect()
+    }
+
+    /// Returns the raw reports of the asset, one per oracle. Unless `include_stale` is true,
+    /// only the reports within the recency duration of the asset are returned.
+    pub fn get_asset_reports(&self, asset_id: AssetId, include_stale: Option<bool>) -> Vec<Report> {
+        let timestamp_cut = if include_stale.unwrap_or(false) {
+            0
+        } else {
+            self.internal_timestamp_cut(&asset_id, env::block_timestamp())
+        };
+        self.internal_get_asset(&self.internal_resolve_asset_id(&asset_id))
+            .map(|asset| {
+                asset
+                    .reports
+                    .into_iter()
+                    .filter(|report| report.timestamp >= timestamp_cut)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the asset ID suffixes understood by `get_price_data`. Keep in sync with
+    /// `internal_get_price`.
+    pub fn get_supported_suffixes(&self) -> Vec<SuffixSpec> {
+        vec![SuffixSpec {
+            pattern: "#<period_sec>".to_string(),
+            description: "EMA of the asset's median price over the given period in seconds. \
+                Only recognized if the asset has an EMA with this period, otherwise the ID is \
+                looked up literally"
+                .to_string(),
+            example: "wrap.near#3600".to_string(),
+        }]
+    }
+
+    /// Explains how `get_price_data` resolves the given asset ID, e.g. `wrap.near#3600` is the
+    /// 1 hour EMA of `wrap.near`.
+    pub fn explain_asset_id(&self, asset_id: AssetId) -> ResolutionTrace {
+        let mut steps = vec![];
+        let base_asset_id = if let Some((base_asset_id, period_sec)) =
+            self.internal_parse_ema_query(&asset_id)
+        {
+            steps.push(format!("EMA period {}", period_sec));
+            base_asset_id.to_string()
+        } else {
+            steps.push("spot median".to_string());
+            asset_id.clone()
+        };
+        let resolved_asset_id = self.internal_resolve_asset_id(&base_asset_id);
+        if resolved_asset_id != base_asset_id {
+            steps.push(format!("alias of {}", resolved_asset_id));
+        }
+        let base_asset_id = resolved_asset_id;
+        let source_asset_ids = if self.assets.contains_key(&base_asset_id) {
+            vec![base_asset_id]
+        } else {
+            steps.push(format!("unknown asset {}", base_asset_id));
+            vec![]
+        };
+        ResolutionTrace {
+            asset_id,
+            steps,
+            source_asset_ids,
+        }
+    }
+
+    /// Returns the prices of the given assets, or of a page of all assets when `asset_ids` is
+    /// `None`. A page is capped at `max_price_data_assets` assets, so large deployments must
+    /// paginate with `from_index` and `limit`.
+    pub fn get_price_data(
+        &self,
+        asset_ids: Option<Vec<AssetId>>,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> PriceData {
+        let asset_ids =
+            asset_ids.unwrap_or_else(|| self.internal_price_data_asset_ids(from_index, limit));
+        self.internal_compute_price_data(Some(asset_ids))
+    }
+
+    /// Same as `get_price_data`, but every price is rescaled to `target_decimals`, so the
+    /// multipliers are directly comparable. A price that overflows is returned as `None`.
+    pub fn get_price_data_normalized(
+        &self,
+        asset_ids: Option<Vec<AssetId>>,
+        target_decimals: u8,
+    ) -> PriceData {
+        assert!(
+            target_decimals <= MAX_VALID_DECIMALS,
+            "Target decimals can't exceed {}",
+            MAX_VALID_DECIMALS
+        );
+        let mut price_data = self.internal_compute_price_data(asset_ids);
+        for asset_price in price_data.prices.iter_mut() {
+            asset_price.price = asset_price
+                .price
+                .and_then(|price| price.rescale(target_decimals));
+        }
+        price_data
+    }
+
+    /// Same as `get_price_data`, but each query can override the recency duration, e.g. a tight
+    /// window for a volatile asset and a loose one for a stable asset.
+    pub fn get_price_data_multi(&self, requests: Vec<PriceQuery>) -> PriceDataMulti {
+        let timestamp = env::block_timestamp();
+        let min_num_recent_reports = self.internal_min_num_recent_reports();
+        let inactive_oracle_ids = self.internal_inactive_oracle_ids();
+
+        PriceDataMulti {
+            timestamp,
+            prices: requests
+                .into_iter()
+                .map(|PriceQuery { asset_id, recency_duration_sec }| {
+                    let recency_duration_sec = recency_duration_sec
+                        .unwrap_or_else(|| self.internal_recency_duration_sec(&asset_id));
+                    let timestamp_cut = timestamp.saturating_sub(to_nano(recency_duration_sec));
+                    let AssetOptionalPrice {
+                        asset_id,
+                        price,
+                        status,
+                        ..
+                    } = self.internal_get_price(
+                        asset_id,
+                        timestamp_cut,
+                        min_num_recent_reports,
+                        &inactive_oracle_ids,
+                    );
+                    AssetQueryPrice {
+                        asset_id,
+                        price,
+                        status,
+                        recency_duration_sec,
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Same as `get_price_data`, but distinguishes stale prices from unknown assets. For stale
+    /// prices, returns the median of all the reports regardless of their age.
+    pub fn get_price_data_freshness(
+        &self,
+        asset_ids: Option<Vec<AssetId>>,
+    ) -> Vec<AssetPriceFreshness> {
+        let asset_ids = asset_ids.unwrap_or_else(|| self.internal_price_data_asset_ids(None, None));
+        let timestamp = env::block_timestamp();
+        let min_num_recent_reports = self.internal_min_num_recent_reports();
+        let inactive_oracle_ids = self.internal_inactive_oracle_ids();
+        asset_ids
+            .into_iter()
+            .map(|asset_id| {
+                let timestamp_cut = self.internal_timestamp_cut(&asset_id, timestamp);
+                let served = self.internal_get_price(
+                    asset_id.clone(),
+                    timestamp_cut,
+                    min_num_recent_reports,
+                    &inactive_oracle_ids,
+                );
+                let freshness = if let Some(price) = served.price {
+                    PriceFreshness::Fresh(price)
+                } else if served.status.is_some() {
+                    PriceFreshness::Unknown
+                } else {
+                    self.internal_get_asset(&self.internal_resolve_asset_id(&asset_id))
+                        .and_then(|asset| {
+                            let last_timestamp =
+                                asset.reports.iter().map(|rp| rp.timestamp).max()?;
+                            let price =
+                                asset.active_median_price(0, 1, &inactive_oracle_ids)?;
+                            Some(PriceFreshness::Stale(price, last_timestamp))
+                        })
+                        .unwrap_or(PriceFreshness::Unknown)
+                };
+                AssetPriceFreshness {
+                    asset_id,
+                    freshness,
+                }
+            })
+            .collect()
+    }
+
+    /// Same as `get_price_data`, but when an asset has some fresh reports yet falls short of
+    /// the quorum, returns the median of the available fresh reports flagged as `degraded`.
+    /// Opt-in for consumers that prefer weakly-backed data over no data during outages.
+    pub fn get_price_data_degraded(&self, asset_ids: Option<Vec<AssetId>>) -> Vec<AssetDegradedPrice> {
+        let asset_ids = asset_ids.unwrap_or_else(|| self.internal_price_data_asset_ids(None, None));
+        let timestamp = env::block_timestamp();
+        let min_num_recent_reports = self.internal_min_num_recent_reports();
+        let inactive_oracle_ids = self.internal_inactive_oracle_ids();
+        asset_ids
+            .into_iter()
+            .map(|asset_id| {
+                let timestamp_cut = self.internal_timestamp_cut(&asset_id, timestamp);
+                let num_fresh = self
+                    .internal_get_asset(&self.internal_resolve_asset_id(&asset_id))
+                    .and_then(|asset| {
+                        asset.fresh_price_range(timestamp_cut, &inactive_oracle_ids)
+                    })
+                    .map_or(0, |(_, _, num_fresh)| num_fresh);
+                let quorum_price = self.internal_get_price(
+                    asset_id.clone(),
+                    timestamp_cut,
+                    min_num_recent_reports,
+                    &inactive_oracle_ids,
+                );
+                if quorum_price.price.is_some()
+                    || quorum_price.status == Some(PriceStatus::SpotDisabled)
+                {
+                    return AssetDegradedPrice {
+                        asset_id,
+                        price: quorum_price.price,
+                        degraded: false,
+                        num_fresh,
+                    };
+                }
+                let price = self
+                    .internal_get_price(asset_id.clone(), timestamp_cut, 1, &inactive_oracle_ids)
+                    .price;
+                AssetDegradedPrice {
+                    asset_id,
+                    price,
+                    degraded: price.is_some(),
+                    num_fresh,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the spot price and all fresh EMAs of the asset in a single typed struct, so
+    /// callers don't have to build and split `#period_sec` asset IDs.
+    pub fn get_combined_price(&self, asset_id: AssetId) -> CombinedPrice {
+        let timestamp_cut = self.internal_timestamp_cut(&asset_id, env::block_timestamp());
+        let spot = self
+            .internal_get_spot_price(
+                asset_id.clone(),
+                timestamp_cut,
+                self.internal_min_num_recent_reports(),
+                &self.internal_inactive_oracle_ids(),
+            )
+            .price;
+        let asset = self.internal_get_asset(&asset_id);
+        CombinedPrice {
+            spot,
+            emas: asset
+                .as_ref()
+                .map(|asset| {
+                    asset
+                        .emas
+                        .iter()
+                        .filter(|ema| ema.timestamp >= timestamp_cut)
+                        .filter_map(|ema| ema.price.map(|price| (ema.period_sec, price)))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            timestamp: asset.and_then(|asset| asset.reports.iter().map(|rp| rp.timestamp).max()),
+            asset_id,
+        }
+    }
+
+    /// Checks whether each price of a previously fetched `PriceData` still matches the price the
+    /// contract would serve now. Prices are compared exactly, regardless of their decimals.
+    pub fn verify_price_data(&self, data: PriceData) -> Vec<(AssetId, bool)> {
+        let timestamp = env::block_timestamp();
+        let min_num_recent_reports = self.internal_min_num_recent_reports();
+        let inactive_oracle_ids = self.internal_inactive_oracle_ids();
+        data.prices
+            .into_iter()
+            .map(|AssetOptionalPrice { asset_id, price, .. }| {
+                let timestamp_cut = self.internal_timestamp_cut(&asset_id, timestamp);
+                let current = self
+                    .internal_get_price(
+                        asset_id.clone(),
+                        timestamp_cut,
+                        min_num_recent_reports,
+                        &inactive_oracle_ids,
+                    )
+                    .price;
+                (asset_id, current == price)
+            })
+            .collect()
+    }
+
+    /// For each asset, tries the given recency windows from the tightest to the loosest and
+    /// returns the first price that is backed by a quorum of fresh reports, along with the
+    /// window that was used.
+    pub fn get_price_data_cascade(
+        &self,
+        asset_ids: Option<Vec<AssetId>>,
+        windows: Vec<DurationSec>,
+    ) -> Vec<AssetCascadePrice> {
+        assert!(!windows.is_empty(), "At least one window is required");
+        let mut windows = windows;
+        windows.sort_unstable();
+        let asset_ids = asset_ids.unwrap_or_else(|| self.internal_price_data_asset_ids(None, None));
+        let timestamp = env::block_timestamp();
+        let min_num_recent_reports = self.internal_min_num_recent_reports();
+        let inactive_oracle_ids = self.internal_inactive_oracle_ids();
+        asset_ids
+            .into_iter()
+            .map(|asset_id| {
+                windows
+                    .iter()
+                    .find_map(|window| {
+                        let timestamp_cut = timestamp.saturating_sub(to_nano(*window));
+                        let price = self
+                            .internal_get_price(
+                                asset_id.clone(),
+                                timestamp_cut,
+                                min_num_recent_reports,
+                                &inactive_oracle_ids,
+                            )
+                            .price?;
+                        Some(AssetCascadePrice {
+                            asset_id: asset_id.clone(),
+                            price: Some(price),
+                            recency_duration_sec: Some(*window),
+                        })
+                    })
+                    .unwrap_or(AssetCascadePrice {
+                        asset_id,
+                        price: None,
+                        recency_duration_sec: None,
+                    })
+            })
+            .collect()
+    }
+
+    /// Returns the median price of each asset together with the lowest and highest fresh
+    /// reports and the number of fresh reports, so callers can gauge oracle agreement.
+    pub fn get_price_data_with_spread(&self, asset_ids: Option<Vec<AssetId>>) -> PriceDataWithSpread {
+        let asset_ids = asset_ids.unwrap_or_else(|| self.internal_price_data_asset_ids(None, None));
+        let timestamp = env::block_timestamp();
+        let min_num_recent_reports = self.internal_min_num_recent_reports();
+        let inactive_oracle_ids = self.internal_inactive_oracle_ids();
+
+        PriceDataWithSpread {
             timestamp,
             recency_duration_sec: self.recency_duration_sec,
             prices: asset_ids
                 .into_iter()
                 .map(|asset_id| {
-                    // EMA for a specific asset, e.g. wrap.near#3600 is 1 hour EMA for wrap.near
-                    if let Some((base_asset_id, period_sec)) = asset_id.split_once('#') {
-                        let period_sec: DurationSec =
-                            period_sec.parse().expect("Failed to parse EMA period");
-                        let asset = self.internal_get_asset(&base_asset_id.to_string());
-                        AssetOptionalPrice {
-                            asset_id,
-                            price: asset.and_then(|asset| {
-                                asset
-                                    .emas
-                                    .into_iter()
-                                    .find(|ema| ema.period_sec == period_sec)
-                                    .filter(|ema| ema.timestamp >= timestamp_cut)
-                                    .and_then(|ema| ema.price)
-                            }),
-                        }
-                    } else {
-                        let asset = self.internal_get_asset(&asset_id);
-                        AssetOptionalPrice {
-                            asset_id,
-                            price: asset.and_then(|asset| {
-                                asset.median_price(timestamp_cut, min_num_recent_reports)
-                            }),
-                        }
+                    let timestamp_cut = self.internal_timestamp_cut(&asset_id, timestamp);
+                    let asset = self.internal_get_asset(&asset_id);
+                    let price = asset.as_ref().and_then(|asset| {
+                        asset.active_median_price(
+                            timestamp_cut,
+                            min_num_recent_reports,
+                            &inactive_oracle_ids,
+                        )
+                    });
+                    let range = asset.as_ref().and_then(|asset| {
+                        asset.fresh_price_range(timestamp_cut, &inactive_oracle_ids)
+                    });
+                    AssetPriceSpread {
+                        asset_id,
+                        price,
+                        min_price: range.map(|(min_price, _, _)| min_price),
+                        max_price: range.map(|(_, max_price, _)| max_price),
+                        num_reports: range.map_or(0, |(_, _, num_reports)| num_reports),
                     }
                 })
                 .collect(),
         }
     }
 
+    /// Returns the median and the mean of each asset side by side. A large gap between them
+    /// signals outliers among the fresh reports.
+    pub fn get_price_data_stats(&self, asset_ids: Option<Vec<AssetId>>) -> Vec<AssetPriceStats> {
+        let asset_ids = asset_ids.unwrap_or_else(|| self.internal_price_data_asset_ids(None, None));
+        let timestamp = env::block_timestamp();
+        let min_num_recent_reports = self.internal_min_num_recent_reports();
+        let inactive_oracle_ids = self.internal_inactive_oracle_ids();
+        asset_ids
+            .into_iter()
+            .map(|asset_id| match self.internal_get_asset(&asset_id) {
+                Some(asset) => {
+                    let timestamp_cut = self.internal_timestamp_cut(&asset_id, timestamp);
+                    asset.price_stats(
+                        asset_id,
+                        timestamp_cut,
+                        min_num_recent_reports,
+                        &inactive_oracle_ids,
+                    )
+                }
+                None => AssetPriceStats {
+                    asset_id,
+                    median: None,
+                    mean: None,
+                    spread_bps: None,
+                    num_fresh: 0,
+                },
+            })
+            .collect()
+    }
+
+    /// Returns how old the data backing each price of `get_price_data` is.
+    pub fn get_price_data_median_age(&self, asset_ids: Option<Vec<AssetId>>) -> Vec<AssetPriceAge> {
+        let asset_ids = asset_ids.unwrap_or_else(|| self.internal_price_data_asset_ids(None, None));
+        let timestamp = env::block_timestamp();
+        let min_num_recent_reports = self.internal_min_num_recent_reports();
+        let inactive_oracle_ids = self.internal_inactive_oracle_ids();
+        asset_ids
+            .into_iter()
+            .map(|asset_id| {
+                let timestamp_cut = self.internal_timestamp_cut(&asset_id, timestamp);
+                match self.internal_get_asset(&self.internal_resolve_asset_id(&asset_id)) {
+                    Some(asset) => {
+                        let min_num_recent_reports =
+                            asset.min_num_recent_reports(min_num_recent_reports);
+                        asset.report_ages(
+                            asset_id,
+                            timestamp,
+                            timestamp_cut,
+                            min_num_recent_reports,
+                            &inactive_oracle_ids,
+                        )
+                    }
+                    None => AssetPriceAge {
+                        asset_id,
+                        median_age_sec: None,
+                        max_age_sec: None,
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// Converts a raw token `amount` into its value with `target_decimals` using the current
+    /// median price of the asset. Returns `None` if there is no price or on overflow.
+    pub fn convert(
+        &self,
+        asset_id: AssetId,
+        amount: U128,
+        token_decimals: u8,
+        target_decimals: u8,
+    ) -> Option<U128> {
+        let timestamp_cut = self.internal_timestamp_cut(&asset_id, env::block_timestamp());
+        self.internal_get_spot_price(
+            asset_id,
+            timestamp_cut,
+            self.internal_min_num_recent_reports(),
+            &self.internal_inactive_oracle_ids(),
+        )
+        .price?
+            .to_amount(amount.0, token_decimals, target_decimals)
+            .map(U128)
+    }
+
+    /// Returns the price at the given percentile of the fresh reports, e.g. the 25th percentile
+    /// for a conservative price. The 50th percentile is the median, without outlier filtering.
+    /// Requires the same quorum as `get_price_data`.
+    pub fn get_percentile_price(&self, asset_id: AssetId, percentile: u8) -> Option<Price> {
+        assert!(
+            (1..=100).contains(&percentile),
+            "Percentile must be within [1, 100]"
+        );
+        let timestamp_cut = self.internal_timestamp_cut(&asset_id, env::block_timestamp());
+        let asset = self.internal_get_asset(&self.internal_resolve_asset_id(&asset_id))?;
+        if asset.disabled {
+            return None;
+        }
+        asset.percentile_price(
+            percentile,
+            timestamp_cut,
+            asset.min_num_recent_reports(self.internal_min_num_recent_reports()),
+            &self.internal_inactive_oracle_ids(),
+        )
+    }
+
+    /// Returns the price of `base` denominated in `quote`, e.g. ETH/BTC from the two USD
+    /// prices, with the given decimals. Returns `None` if either price isn't served by
+    /// `get_price_data`, or on overflow.
+    pub fn get_cross_price(
+        &self,
+        base: AssetId,
+        quote: AssetId,
+        target_decimals: u8,
+    ) -> Option<Price> {
+        let timestamp = env::block_timestamp();
+        let min_num_recent_reports = self.internal_min_num_recent_reports();
+        let inactive_oracle_ids = self.internal_inactive_oracle_ids();
+        let [base_price, quote_price] = [base, quote].map(|asset_id| {
+            let timestamp_cut = self.internal_timestamp_cut(&asset_id, timestamp);
+            self.internal_get_price(
+                asset_id,
+                timestamp_cut,
+                min_num_recent_reports,
+                &inactive_oracle_ids,
+            )
+            .price
+        });
+        base_price?.checked_div(&quote_price?, target_decimals)
+    }
+
+    /// Returns the number of fresh reports currently required to serve the median of the
+    /// asset: the majority of the oracles, raised by `global_min_reports` and the asset's
+    /// `min_reporters`.
+    pub fn get_effective_quorum(&self, asset_id: AssetId) -> u32 {
+        let asset = self
+            .internal_get_asset(&self.internal_resolve_asset_id(&asset_id))
+            .expect("Missing an asset");
+        asset.min_num_recent_reports(self.internal_min_num_recent_reports()) as u32
+    }
+
+    /// Returns whether an oracle should report the proposed price, i.e. the heartbeat of the
+    /// asset elapsed since its latest report, or the proposed price deviates from the current
+    /// median beyond the threshold. Always true without a fresh median, or if the asset has
+    /// no update policy.
+    pub fn should_update(&self, asset_id: AssetId, proposed_price: Price) -> bool {
+        let timestamp = env::block_timestamp();
+        let timestamp_cut = self.internal_timestamp_cut(&asset_id, timestamp);
+        let asset = self
+            .internal_get_asset(&self.internal_resolve_asset_id(&asset_id))
+            .expect("Missing an asset");
+        if asset.heartbeat_sec.is_none() && asset.deviation_threshold_bps.is_none() {
+            return true;
+        }
+        let Some(median_price) = asset.active_median_price(
+            timestamp_cut,
+            asset.min_num_recent_reports(self.internal_min_num_recent_reports()),
+            &self.internal_inactive_oracle_ids(),
+        ) else {
+            return true;
+        };
+        let heartbeat_elapsed = asset.heartbeat_sec.is_some_and(|heartbeat_sec| {
+            let last_report = asset.reports.iter().map(|rp| rp.timestamp).max().unwrap_or(0);
+            last_report + to_nano(heartbeat_sec) <= timestamp
+        });
+        let deviates = asset.deviation_threshold_bps.is_some_and(|bps| {
+            median_price
+                .deviation_band(bps)
+                .is_none_or(|(lower, upper)| proposed_price < lower || proposed_price > upper)
+        });
+        heartbeat_elapsed || deviates
+    }
+
+    /// Returns the price of the asset rescaled to each of the target decimals, with `None` for
+    /// targets that overflow.
+    pub fn get_price_multi_decimals(
+        &self,
+        asset_id: AssetId,
+        targets: Vec<u8>,
+    ) -> Vec<(u8, Option<Price>)> {
+        let timestamp_cut = self.internal_timestamp_cut(&asset_id, env::block_timestamp());
+        let price = self
+            .internal_get_price(
+                asset_id,
+                timestamp_cut,
+                self.internal_min_num_recent_reports(),
+                &self.internal_inactive_oracle_ids(),
+            )
+            .price;
+        targets
+            .into_iter()
+            .map(|decimals| (decimals, price.and_then(|price| price.rescale(decimals))))
+            .collect()
+    }
+
     /// Returns price data for a given oracle ID and given list of asset IDs.
     /// If recency_duration_sec is given, then it uses the given duration instead of the one from
     /// the contract config.
@@ -182,12 +953,12 @@ impl Contract {
         asset_ids: Option<Vec<AssetId>>,
         recency_duration_sec: Option<DurationSec>,
     ) -> PriceData {
-        let asset_ids = asset_ids.unwrap_or_else(|| self.assets.keys().cloned().collect());
+        let asset_ids = asset_ids.unwrap_or_else(|| self.internal_price_data_asset_ids(None, None));
         let timestamp = env::block_timestamp();
         let recency_duration_sec = recency_duration_sec.unwrap_or(self.recency_duration_sec);
         let timestamp_cut = timestamp.saturating_sub(to_nano(recency_duration_sec));
 
-        let oracle_id: AccountId = account_id.into();
+        let oracle_id = account_id;
         PriceData {
             timestamp,
             recency_duration_sec,
@@ -197,66 +968,206 @@ impl Contract {
                     let asset = self.internal_get_asset(&asset_id);
                     AssetOptionalPrice {
                         asset_id,
-                        price: asset.and_then(|asset| {
-                            asset
-                                .reports
-                                .into_iter()
-                                .find(|report| report.oracle_id == oracle_id)
-                                .filter(|report| report.timestamp >= timestamp_cut)
-                                .map(|report| report.price)
-                        }),
+                        price: asset
+                            .and_then(|asset| asset.oracle_price(&oracle_id, timestamp_cut)),
+                        status: None,
+                        source_asset_id: None,
                     }
                 })
                 .collect(),
         }
     }
 
-    pub fn report_prices(&mut self, prices: Vec<AssetPrice>, claim_near: Option<bool>) {
-        assert!(!prices.is_empty());
-        let oracle_id = env::predecessor_account_id();
+    /// Returns the fresh price reported by every oracle for the given asset, `None` for the
+    /// oracles without a fresh report. Same recency rules as `get_oracle_price_data`.
+    pub fn get_asset_oracle_prices(
+        &self,
+        asset_id: AssetId,
+        recency_duration_sec: Option<DurationSec>,
+    ) -> Vec<(AccountId, Option<Price>)> {
+        let recency_duration_sec = recency_duration_sec.unwrap_or(self.recency_duration_sec);
+        let timestamp_cut = env::block_timestamp().saturating_sub(to_nano(recency_duration_sec));
+        let asset = self.internal_get_asset(&asset_id);
+        self.oracles
+            .keys()
+            .map(|oracle_id| {
+                let price = asset
+                    .as_ref()
+                    .and_then(|asset| asset.oracle_price(oracle_id, timestamp_cut));
+                (oracle_id.clone(), price)
+            })
+            .collect()
+    }
+
+    /// Reports prices for the given assets. When `strict` is true, every asset ID and price is
+    /// validated before any state is mutated, so a single bad entry rejects the whole batch.
+    /// Otherwise unknown asset IDs and prices outside of the asset bounds are logged and skipped.
+    pub fn report_prices(
+        &mut self,
+        prices: Vec<AssetPrice>,
+        claim_near: Option<bool>,
+        strict: Option<bool>,
+    ) {
+        self.internal_report_prices(
+            env::predecessor_account_id(),
+            prices,
+            claim_near.unwrap_or(false),
+            strict.unwrap_or(false),
+        );
+    }
+
+    /// Applies the prices reported by the oracle, see `report_prices`.
+    pub(crate) fn internal_report_prices(
+        &mut self,
+        oracle_id: AccountId,
+        prices: Vec<AssetPrice>,
+        claim_near: bool,
+        strict: bool,
+    ) {
+        assert!(!prices.is_empty(), "{}: No prices in the report", E_EMPTY_REPORT);
+        assert!(
+            prices.len() <= self.max_report_batch as usize,
+            "{}: Too many prices in a report, the maximum is {}",
+            E_BATCH_TOO_LARGE,
+            self.max_report_batch
+        );
+        if strict {
+            for AssetPrice { asset_id, price, .. } in prices.iter() {
+                let asset = self
+                    .internal_get_asset(asset_id)
+                    .unwrap_or_else(|| {
+                        env::panic_str(&format!(
+                            "{}: Unknown asset ID: {}",
+                            E_UNKNOWN_ASSET, asset_id
+                        ))
+                    });
+                asset.assert_valid_price(price);
+                asset.assert_validated_price(price);
+                assert!(
+                    asset.is_within_price_bounds(price),
+                    "{}: Price of {} is outside of the asset bounds",
+                    E_PRICE_BOUNDS,
+                    asset_id
+                );
+            }
+        }
         let timestamp = env::block_timestamp();
 
         // Oracle stats
-        let mut oracle = self.internal_get_oracle(&oracle_id).expect("Not an oracle");
+        let mut oracle = self
+            .internal_get_oracle(&oracle_id)
+            .unwrap_or_else(|| env::panic_str(&format!("{}: Not an oracle", E_NOT_ORACLE)));
         
         // Require approved codehash for price reporting
         self.require_approved_codehash(&oracle_id, &oracle);
+        if self.require_approved_checksum {
+            self.require_approved_checksum(&oracle_id, &oracle);
+        }
+        assert!(
+            !self.oracle_deactivations.contains_key(&oracle_id),
+            "{}: Oracle is scheduled for removal",
+            E_ORACLE_REMOVAL
+        );
+        // EMAs assume time moves forward between the reports.
+        assert!(
+            timestamp > oracle.last_report,
+            "{}: Report timestamp must be later than the last report of the oracle",
+            E_TIMESTAMP
+        );
+        assert!(
+            self.min_report_interval_sec == 0
+                || oracle.last_report == 0
+                || timestamp >= oracle.last_report + to_nano(self.min_report_interval_sec),
+            "{}: Oracle reports too frequently, the minimum interval is {} seconds",
+            E_REPORT_INTERVAL,
+            self.min_report_interval_sec
+        );
         oracle.last_report = timestamp;
         oracle.price_reports += prices.len() as u64;
 
-        if claim_near.unwrap_or(false) && oracle.last_near_claim + NEAR_CLAIM_DURATION <= timestamp
+        if claim_near
+            && oracle.last_near_claim + to_nano(self.near_claim_cooldown_sec) <= timestamp
         {
-            let liquid_balance = env::account_balance().as_yoctonear() + env::account_locked_balance().as_yoctonear()
-                - env::storage_byte_cost().as_yoctonear() * u128::from(env::storage_usage());
-            if liquid_balance > (self.near_claim_amount.as_yoctonear() + SAFETY_MARGIN_NEAR_CLAIM.as_yoctonear()) {
+            let near_claim_amount = oracle.claim_amount.unwrap_or(self.near_claim_amount);
+            if self.internal_can_pay_near_claim(near_claim_amount) {
                 oracle.last_near_claim = timestamp;
-                Promise::new(oracle_id.clone()).transfer(self.near_claim_amount);
+                oracle.total_near_claimed = oracle.total_near_claimed.saturating_add(near_claim_amount);
+                Promise::new(oracle_id.clone()).transfer(near_claim_amount);
+            } else {
+                log!(
+                    "EVENT_JSON:{}",
+                    serde_json::json!({
+                        "standard": "price-oracle",
+                        "version": "1.0.0",
+                        "event": "near_claim_skipped",
+                        "data": [{
+                            "oracle_id": oracle_id,
+                            "amount": U128(near_claim_amount.as_yoctonear()),
+                            "liquid_balance": U128(self.internal_liquid_balance()),
+                        }]
+                    })
+                );
             }
         }
 
-        self.internal_set_oracle(&oracle_id, oracle);
-
         // Updating prices
-        for AssetPrice { asset_id, price } in prices {
+        let inactive_oracle_ids = self.internal_inactive_oracle_ids();
+        for AssetPrice {
+            asset_id,
+            price,
+            observed_at,
+        } in prices
+        {
             price.assert_valid();
             if let Some(mut asset) = self.internal_get_asset(&asset_id) {
+                asset.assert_valid_price(&price);
+                asset.assert_validated_price(&price);
+                if !asset.is_within_price_bounds(&price) {
+                    log!("Warning! Price of {} is outside of the asset bounds", asset_id);
+                    continue;
+                }
+                let recency_duration_sec =
+                    asset.recency_duration_sec.unwrap_or(self.recency_duration_sec);
+                let timestamp_cut = timestamp.saturating_sub(to_nano(recency_duration_sec));
+                let min_num_recent_reports =
+                    asset.min_num_recent_reports(self.internal_min_num_recent_reports());
+                asset.assert_price_within_deviation(&price, timestamp_cut, min_num_recent_reports);
+                if let Some(observed_at) = observed_at {
+                    assert!(
+                        observed_at <= timestamp && observed_at >= timestamp_cut,
+                        "{}: Observation time of {} is outside of the accepted window",
+                        E_OBSERVED_AT,
+                        asset_id
+                    );
+                }
                 asset.remove_report(&oracle_id);
+                if let Some(prune_threshold_sec) = self.report_prune_threshold_sec {
+                    asset.prune_reports(timestamp.saturating_sub(to_nano(prune_threshold_sec)));
+                }
+                asset.last_updated = timestamp;
+                let report_timestamp = observed_at.unwrap_or(timestamp);
+                oracle.fresh_until = std::cmp::max(
+                    oracle.fresh_until,
+                    report_timestamp + to_nano(recency_duration_sec),
+                );
                 asset.add_report(Report {
                     oracle_id: oracle_id.clone(),
-                    timestamp,
+                    timestamp: report_timestamp,
                     price,
                 });
-                if !asset.emas.is_empty() {
-                    let timestamp_cut =
-                        timestamp.saturating_sub(to_nano(self.recency_duration_sec));
-                    let min_num_recent_reports =
-                        std::cmp::max(1, (self.oracles.len() + 1) / 2) as usize;
-                    if let Some(median_price) =
-                        asset.median_price(timestamp_cut, min_num_recent_reports)
-                    {
+                let has_history = self.asset_histories.contains_key(&asset_id);
+                if !asset.emas.is_empty() || has_history {
+                    if let Some(median_price) = asset.active_median_price(
+                        timestamp_cut,
+                        min_num_recent_reports,
+                        &inactive_oracle_ids,
+                    ) {
                         for ema in asset.emas.iter_mut() {
                             ema.recompute(median_price, timestamp);
                         }
+                        if has_history {
+                            self.internal_record_price_snapshot(&asset_id, median_price, timestamp);
+                        }
                     }
                 }
                 self.internal_set_asset(&asset_id, asset);
@@ -264,6 +1175,7 @@ impl Contract {
                 log!("Warning! Unknown asset ID: {}", asset_id);
             }
         }
+        self.internal_set_oracle(&oracle_id, oracle);
     }
 
     pub fn register_agent(
@@ -273,42 +1185,55 @@ impl Contract {
         checksum: String,
         tcb_info: String,
     ) -> bool {
-        let collateral_data = crate::collateral::get_collateral(collateral);
-        let quote = decode(quote_hex).unwrap();
-        let now = env::block_timestamp() / 1000000000;
-        let result = verify::verify(&quote, &collateral_data, now).expect("report is not verified");
-        let report = result.report.as_td10().unwrap();
-        let report_data = format!("{}", String::from_utf8_lossy(&report.report_data));
+        let verification = self
+            .internal_verify_attestation(quote_hex, collateral, tcb_info)
+            .unwrap_or_else(|err| env::panic_str(&err));
 
         // verify the predecessor matches the report data
-        require!(
-            env::predecessor_account_id() == report_data,
-            format!("predecessor_account_id != report_data: {}", report_data)
-        );
-
-        let rtmr3 = encode(report.rt_mr3.to_vec());
-        let (shade_agent_api_image, shade_agent_app_image) =
-            crate::collateral::verify_codehash(tcb_info, rtmr3);
+        crate::collateral::verify_report_data(
+            &decode(&verification.report_data).unwrap(),
+            env::predecessor_account_id().as_str(),
+        )
+        .unwrap_or_else(|err| env::panic_str(&err));
 
         // verify the code hashes are approved
-        require!(self.approved_codehashes.contains(&shade_agent_api_image));
-        require!(self.approved_codehashes.contains(&shade_agent_app_image));
+        require!(verification.api_codehash_approved);
+        require!(verification.app_codehash_approved);
 
         let predecessor = env::predecessor_account_id();
-        
-        // Check if oracle already exists
-        assert!(self.internal_get_oracle(&predecessor).is_none(), "Oracle already exists");
-        
+
         // Create oracle with codehash information
         let mut oracle = Oracle::new();
-        oracle.codehash = Some(shade_agent_app_image);
+        oracle.codehash = Some(verification.app_codehash);
         oracle.checksum = Some(checksum);
-        
-        self.internal_set_oracle(&predecessor, oracle);
+
+        self.internal_add_oracle(&predecessor, oracle);
 
         true
     }
 
+    /// Runs the quote and codehash checks of `register_agent` without registering, so agent
+    /// operators can debug their attestation before sending a transaction. Returns the error
+    /// instead of panicking if the quote or tcb_info doesn't verify. The report data isn't
+    /// matched against the caller.
+    pub fn verify_agent(
+        &self,
+        quote_hex: String,
+        collateral: String,
+        tcb_info: String,
+    ) -> AgentVerificationResult {
+        match self.internal_verify_attestation(quote_hex, collateral, tcb_info) {
+            Ok(attestation) => AgentVerificationResult {
+                attestation: Some(attestation),
+                error: None,
+            },
+            Err(error) => AgentVerificationResult {
+                attestation: None,
+                error: Some(error),
+            },
+        }
+    }
+
     pub fn get_agent(&self, account_id: AccountId) -> Worker {
         self.worker_by_account_id
             .get(&account_id)
@@ -316,28 +1241,134 @@ impl Contract {
             .to_owned()
     }
     
+    /// Calls `oracle_on_call` on the receiver with the price data. `callback_gas` is the gas
+    /// kept for this call's own work after the promise, 10 Tgas by default and at most 100 Tgas.
+    /// The gas of `on_oracle_call_complete` and the fees of its receipt are kept on top of that.
+    /// The receiver gets the rest.
+    ///
+    /// The price data is fully computed before the promise is created, and neither this call
+    /// nor `on_oracle_call_complete` writes state, so the receiver can't observe or interleave
+    /// with a partial update. Any future state change must be made before creating the promise,
+    /// or in the callback once the receiver's result is known.
     #[payable]
     pub fn oracle_call(
         &mut self,
         receiver_id: AccountId,
         asset_ids: Option<Vec<AssetId>>,
         msg: String,
+        callback_gas: Option<Gas>,
     ) -> Promise {
         self.assert_well_paid();
+        self.assert_valid_oracle_call_msg(&msg);
 
         let sender_id = env::predecessor_account_id();
-        let price_data = self.get_price_data(asset_ids);
+        let price_data = self.internal_compute_price_data(asset_ids);
         let remaining_gas = env::prepaid_gas().as_gas() - env::used_gas().as_gas();
-        assert!(remaining_gas >= GAS_FOR_PROMISE.as_gas());
+        let callback_gas = std::cmp::min(
+            callback_gas.unwrap_or(GAS_FOR_PROMISE),
+            MAX_GAS_FOR_PROMISE,
+        );
+        let reserved_gas = callback_gas.as_gas()
+            + GAS_FOR_ON_ORACLE_CALL_COMPLETE.as_gas()
+            + GAS_FOR_CALLBACK_RECEIPT.as_gas();
+        assert!(remaining_gas >= reserved_gas);
 
-        Promise::new(receiver_id)
+        Promise::new(receiver_id.clone())
             .function_call(
                 "oracle_on_call".to_string(),
                 serde_json::to_vec(&(sender_id, price_data, msg)).unwrap(),
                 NO_DEPOSIT,
-                Gas::from_gas(remaining_gas - GAS_FOR_PROMISE.as_gas()),
+                Gas::from_gas(remaining_gas - reserved_gas),
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_ON_ORACLE_CALL_COMPLETE)
+                    .on_oracle_call_complete(receiver_id),
+            )
+    }
+
+    /// Returns whether the receiver's `oracle_on_call` succeeded, so the caller can retry.
+    #[private]
+    pub fn on_oracle_call_complete(&self, receiver_id: AccountId) -> bool {
+        let success = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        if !success {
+            log!("oracle_on_call failed on {}", receiver_id);
+        }
+        success
+    }
+
+    /// Same as `oracle_call`, but sends one price data snapshot to several receivers, each
+    /// with its own `msg`. `callback_gas` is kept for this call's own work as in `oracle_call`,
+    /// along with the gas of `on_oracle_call_batch_complete` and the fees of its receipts. The
+    /// rest is split evenly between the receivers.
+    #[payable]
+    pub fn oracle_call_batch(
+        &mut self,
+        receivers: Vec<(AccountId, String)>,
+        asset_ids: Option<Vec<AssetId>>,
+        callback_gas: Option<Gas>,
+    ) -> Promise {
+        self.assert_well_paid();
+        assert!(!receivers.is_empty(), "No receivers");
+        for (_, msg) in receivers.iter() {
+            self.assert_valid_oracle_call_msg(msg);
+        }
+
+        let sender_id = env::predecessor_account_id();
+        let price_data = self.internal_compute_price_data(asset_ids);
+        let remaining_gas = env::prepaid_gas().as_gas() - env::used_gas().as_gas();
+        let callback_gas = std::cmp::min(
+            callback_gas.unwrap_or(GAS_FOR_PROMISE),
+            MAX_GAS_FOR_PROMISE,
+        );
+        let num_receivers = receivers.len() as u64;
+        let reserved_gas = callback_gas.as_gas()
+            + GAS_FOR_ON_ORACLE_CALL_COMPLETE.as_gas()
+            + num_receivers * GAS_FOR_CALLBACK_RECEIPT.as_gas();
+        let gas_per_receiver = remaining_gas.saturating_sub(reserved_gas) / num_receivers;
+        assert!(
+            gas_per_receiver >= GAS_FOR_PROMISE.as_gas(),
+            "Not enough gas for {} receivers",
+            receivers.len()
+        );
+
+        let receiver_ids: Vec<AccountId> =
+            receivers.iter().map(|(receiver_id, _)| receiver_id.clone()).collect();
+        receivers
+            .into_iter()
+            .map(|(receiver_id, msg)| {
+                Promise::new(receiver_id).function_call(
+                    "oracle_on_call".to_string(),
+                    serde_json::to_vec(&(&sender_id, &price_data, msg)).unwrap(),
+                    NO_DEPOSIT,
+                    Gas::from_gas(gas_per_receiver),
+                )
+            })
+            .reduce(|promise, next| promise.and(next))
+            .unwrap()
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_ON_ORACLE_CALL_COMPLETE)
+                    .on_oracle_call_batch_complete(receiver_ids),
             )
     }
+
+    /// Returns whether each receiver's `oracle_on_call` succeeded, in the order of the receivers.
+    #[private]
+    pub fn on_oracle_call_batch_complete(&self, receiver_ids: Vec<AccountId>) -> Vec<bool> {
+        receiver_ids
+            .iter()
+            .enumerate()
+            .map(|(index, receiver_id)| {
+                let success =
+                    matches!(env::promise_result(index as u64), PromiseResult::Successful(_));
+                if !success {
+                    log!("oracle_on_call failed on {}", receiver_id);
+                }
+                success
+            })
+            .collect()
+    }
 }
 
 impl Default for Contract {
@@ -348,8 +1379,32 @@ impl Default for Contract {
             recency_duration_sec: 0,
             owner_id: "".parse().unwrap(),
             near_claim_amount: NearToken::from_yoctonear(0),
-            approved_codehashes: IterableSet::new(StorageKey::ApprovedCodehashes),
+            approved_codehashes: IterableMap::new(StorageKey::ApprovedCodehashes),
             worker_by_account_id: IterableMap::new(b"b"),
+            max_oracles: None,
+            asset_histories: LookupMap::new(StorageKey::AssetHistories),
+            oracle_deactivations: IterableMap::new(StorageKey::OracleDeactivations),
+            asset_aliases: IterableMap::new(StorageKey::AssetAliases),
+            min_report_interval_sec: 0,
+            pending_owner: None,
+            admins: IterableSet::new(StorageKey::Admins),
+            report_prune_threshold_sec: None,
+            near_claim_cooldown_sec: DEFAULT_NEAR_CLAIM_COOLDOWN_SEC,
+            approved_checksums: IterableSet::new(StorageKey::ApprovedChecksums),
+            require_approved_checksum: false,
+            oracle_nonces: LookupMap::new(StorageKey::OracleNonces),
+            max_report_batch: DEFAULT_MAX_REPORT_BATCH,
+            asset_fallbacks: IterableMap::new(StorageKey::AssetFallbacks),
+            max_oracle_call_msg_len: DEFAULT_MAX_ORACLE_CALL_MSG_LEN,
+            total_bonded: NearToken::from_yoctonear(0),
+            removed_oracle_bonds: LookupMap::new(StorageKey::RemovedOracleBonds),
+            owner_actions: LookupMap::new(StorageKey::OwnerActions),
+            num_owner_actions: 0,
+            global_min_reports: 0,
+            oracle_ids_by_index: Vector::new(StorageKey::OracleIdsByIndex),
+            oracle_indices: LookupMap::new(StorageKey::OracleIndices),
+            claim_safety_margin: DEFAULT_CLAIM_SAFETY_MARGIN,
+            max_price_data_assets: DEFAULT_MAX_PRICE_DATA_ASSETS,
         }
     }
 }
@@ -359,12 +1414,2060 @@ impl Contract {
         assert_one_yocto();
     }
 
-    /// Will throw if oracle is not registered with a codehash in self.approved_codehashes
-    fn require_approved_codehash(&self, oracle_id: &AccountId, oracle: &Oracle) {
-        let codehash = oracle.codehash.as_ref().expect("Oracle must have approved codehash to report prices");
-        require!(
-            self.approved_codehashes.contains(codehash),
-            format!("Oracle {} codehash {} is not approved", oracle_id, codehash)
+    fn assert_valid_oracle_call_msg(&self, msg: &str) {
+        assert!(
+            msg.len() <= self.max_oracle_call_msg_len as usize,
+            "The msg is too long, the maximum is {} bytes",
+            self.max_oracle_call_msg_len
         );
     }
+
+    /// Computes the price data served by `get_price_data` and forwarded by `oracle_call`.
+    /// `None` computes the prices of the first page of assets.
+    pub(crate) fn internal_compute_price_data(&self, asset_ids: Option<Vec<AssetId>>) -> PriceData {
+        let asset_ids = asset_ids.unwrap_or_else(|| self.internal_price_data_asset_ids(None, None));
+        let timestamp = env::block_timestamp();
+        let min_num_recent_reports = self.internal_min_num_recent_reports();
+        let inactive_oracle_ids = self.internal_inactive_oracle_ids();
+
+        PriceData {
+            timestamp,
+            recency_duration_sec: self.recency_duration_sec,
+            prices: asset_ids
+                .into_iter()
+                .map(|asset_id| {
+                    self.internal_get_price_with_fallback(
+                        asset_id,
+                        timestamp,
+                        min_num_recent_reports,
+                        &inactive_oracle_ids,
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns a page of all asset IDs. The page is capped at `max_price_data_assets` IDs.
+    pub(crate) fn internal_price_data_asset_ids(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<AssetId> {
+        let from_index = from_index.unwrap_or(0);
+        let max_limit = u64::from(self.max_price_data_assets);
+        let limit = limit.map_or(max_limit, |limit| limit.min(max_limit));
+        self.assets
+            .keys()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect()
+    }
+
+    /// Computes the price of the queried asset ID as served by `get_price_data`.
+    pub(crate) fn internal_get_price(
+        &self,
+        asset_id: AssetId,
+        timestamp_cut: Timestamp,
+        min_num_recent_reports: usize,
+        inactive_oracle_ids: &HashSet<AccountId>,
+    ) -> AssetOptionalPrice {
+        // EMA for a specific asset, e.g. wrap.near#3600 is 1 hour EMA for wrap.near
+        if let Some((base_asset_id, period_sec)) = self.internal_parse_ema_query(&asset_id) {
+            let asset = self.internal_get_asset(&self.internal_resolve_asset_id(base_asset_id));
+            if asset.as_ref().is_some_and(|asset| asset.disabled) {
+                return AssetOptionalPrice {
+                    asset_id,
+                    price: None,
+                    status: Some(PriceStatus::Disabled),
+                    source_asset_id: None,
+                };
+            }
+            AssetOptionalPrice {
+                asset_id,
+                price: asset.and_then(|asset| {
+                    asset
+                        .emas
+                        .into_iter()
+                        .find(|ema| ema.period_sec == period_sec)
+                        .filter(|ema| ema.timestamp >= timestamp_cut)
+                        .and_then(|ema| ema.price)
+                }),
+                status: None,
+                source_asset_id: None,
+            }
+        } else {
+            self.internal_get_spot_price(
+                asset_id,
+                timestamp_cut,
+                min_num_recent_reports,
+                inactive_oracle_ids,
+            )
+        }
+    }
+
+    /// Same as `internal_get_price`, but follows the fallbacks of the asset while there is no
+    /// fresh price. A price suppressed with a status, e.g. a disabled asset, isn't replaced.
+    pub(crate) fn internal_get_price_with_fallback(
+        &self,
+        asset_id: AssetId,
+        timestamp: Timestamp,
+        min_num_recent_reports: usize,
+        inactive_oracle_ids: &HashSet<AccountId>,
+    ) -> AssetOptionalPrice {
+        let timestamp_cut = self.internal_timestamp_cut(&asset_id, timestamp);
+        let mut result = self.internal_get_price(
+            asset_id,
+            timestamp_cut,
+            min_num_recent_reports,
+            inactive_oracle_ids,
+        );
+        let mut source_asset_id = result.asset_id.clone();
+        for _ in 0..MAX_FALLBACK_DEPTH {
+            if result.price.is_some() || result.status.is_some() {
+                break;
+            }
+            let Some(fallback_id) = self.asset_fallbacks.get(&source_asset_id) else {
+                break;
+            };
+            source_asset_id = fallback_id.clone();
+            let timestamp_cut = self.internal_timestamp_cut(&source_asset_id, timestamp);
+            let fallback = self.internal_get_price(
+                source_asset_id.clone(),
+                timestamp_cut,
+                min_num_recent_reports,
+                inactive_oracle_ids,
+            );
+            if fallback.price.is_some() {
+                result.price = fallback.price;
+                result.status = fallback.status;
+                result.source_asset_id = Some(source_asset_id.clone());
+            }
+        }
+        result
+    }
+
+    /// Computes the spot price of the asset as served by `get_price_data`, aggregated with the
+    /// asset's aggregation method.
+    pub(crate) fn internal_get_spot_price(
+        &self,
+        asset_id: AssetId,
+        timestamp_cut: Timestamp,
+        min_num_recent_reports: usize,
+        inactive_oracle_ids: &HashSet<AccountId>,
+    ) -> AssetOptionalPrice {
+        let asset = match self.internal_get_asset(&self.internal_resolve_asset_id(&asset_id)) {
+            Some(asset) => asset,
+            None => {
+                return AssetOptionalPrice {
+                    asset_id,
+                    price: None,
+                    status: None,
+                    source_asset_id: None,
+                }
+            }
+        };
+        if asset.disabled {
+            return AssetOptionalPrice {
+                asset_id,
+                price: None,
+                status: Some(PriceStatus::Disabled),
+                source_asset_id: None,
+            };
+        }
+        if asset.spot_serving_disabled {
+            return AssetOptionalPrice {
+                asset_id,
+                price: None,
+                status: Some(PriceStatus::SpotDisabled),
+                source_asset_id: None,
+            };
+        }
+        let price = asset.aggregated_price(
+            timestamp_cut,
+            asset.min_num_recent_reports(min_num_recent_reports),
+            inactive_oracle_ids,
+        );
+        if price.is_some_and(|price| asset.deviates_from_ema_guard(&price, timestamp_cut)) {
+            return AssetOptionalPrice {
+                asset_id,
+                price: None,
+                status: Some(PriceStatus::EmaDeviation),
+                source_asset_id: None,
+            };
+        }
+        let status = if price.is_some() && asset.is_reported_off_session(timestamp_cut) {
+            Some(PriceStatus::OffSession)
+        } else {
+            None
+        };
+        AssetOptionalPrice {
+            asset_id,
+            price,
+            status,
+            source_asset_id: None,
+        }
+    }
+
+    /// Number of fresh reports required to serve a median price: a majority of the eligible
+    /// oracles, but at least `global_min_reports`. Inactive oracles aren't eligible, and oracles
+    /// scheduled for removal stop being eligible at their deactivation timestamp. The per-asset
+    /// `min_reporters` can only raise it further, see `Asset::min_num_recent_reports`.
+    pub(crate) fn internal_min_num_recent_reports(&self) -> usize {
+        let timestamp = env::block_timestamp();
+        let num_oracles = self
+            .oracles
+            .iter()
+            .filter(|(account_id, v)| {
+                let oracle: Oracle = (*v).into();
+                oracle.active
+                    && self
+                        .oracle_deactivations
+                        .get(*account_id)
+                        .is_none_or(|deactivate_at| *deactivate_at > timestamp)
+            })
+            .count() as u32;
+        std::cmp::max(1, num_oracles.div_ceil(2)).max(self.global_min_reports) as usize
+    }
+
+    /// Verifies the quote against the collateral and extracts the codehashes from tcb_info.
+    fn internal_verify_attestation(
+        &self,
+        quote_hex: String,
+        collateral: String,
+        tcb_info: String,
+    ) -> Result<AgentAttestation, String> {
+        let collateral_data = crate::collateral::get_collateral(collateral)?;
+        let quote = decode(quote_hex).map_err(|_| "quote_hex should be hex encoded".to_string())?;
+        let now = env::block_timestamp() / 1000000000;
+        let result = verify::verify(&quote, &collateral_data, now)
+            .map_err(|_| "report is not verified".to_string())?;
+        let report = result
+            .report
+            .as_td10()
+            .ok_or_else(|| "report is not a TD10 report".to_string())?;
+        let rtmr3 = encode(report.rt_mr3);
+        self.internal_agent_attestation(&report.report_data, rtmr3, tcb_info)
+    }
+
+    /// Extracts the codehashes from tcb_info, which must replay to the given rtmr3 of a
+    /// verified quote, and checks whether they're approved.
+    pub(crate) fn internal_agent_attestation(
+        &self,
+        report_data: &[u8],
+        rtmr3: String,
+        tcb_info: String,
+    ) -> Result<AgentAttestation, String> {
+        let (api_codehash, app_codehash) = crate::collateral::verify_codehash(tcb_info, rtmr3)?;
+        Ok(AgentAttestation {
+            report_data: encode(report_data),
+            api_codehash_approved: self.internal_is_codehash_approved(&api_codehash),
+            app_codehash_approved: self.internal_is_codehash_approved(&app_codehash),
+            api_codehash,
+            app_codehash,
+        })
+    }
+
+    /// Will throw if oracle is not registered with a codehash in self.approved_codehashes, or
+    /// if the approval of that codehash has expired.
+    fn require_approved_codehash(&self, oracle_id: &AccountId, oracle: &Oracle) {
+        let codehash = oracle.codehash.as_ref().unwrap_or_else(|| {
+            env::panic_str(&format!(
+                "{}: Oracle must have approved codehash to report prices",
+                E_CODEHASH
+            ))
+        });
+        require!(
+            self.internal_is_codehash_approved(codehash),
+            format!("{}: Oracle {} codehash {} is not approved", E_CODEHASH, oracle_id, codehash)
+        );
+    }
+
+    /// Will throw if the checksum the oracle registered with is missing or not approved.
+    fn require_approved_checksum(&self, oracle_id: &AccountId, oracle: &Oracle) {
+        let checksum = oracle
+            .checksum
+            .as_ref()
+            .filter(|checksum| !checksum.is_empty())
+            .unwrap_or_else(|| {
+                env::panic_str(&format!("{}: Oracle {} has no checksum", E_CHECKSUM, oracle_id))
+            });
+        require!(
+            self.approved_checksums.contains(checksum),
+            format!("{}: Oracle {} checksum {} is not approved", E_CHECKSUM, oracle_id, checksum)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::mock::MockAction;
+    use near_sdk::testing_env;
+
+    pub(crate) const CODEHASH: &str =
+        "105015ca023e386df24f80fe45f6545f206df75e8f37debee00f603057da462b";
+
+    pub(crate) fn ts(sec: u32) -> Timestamp {
+        to_nano(1_600_000_000 + sec)
+    }
+
+    pub(crate) fn price(multiplier: u128, decimals: u8) -> Price {
+        Price {
+            multiplier,
+            decimals,
+            negative: false,
+        }
+    }
+
+    pub(crate) fn set_context(predecessor: AccountId, timestamp: Timestamp) {
+        set_context_with_deposit(predecessor, timestamp, NearToken::from_yoctonear(1));
+    }
+
+    pub(crate) fn set_context_with_deposit(
+        predecessor: AccountId,
+        timestamp: Timestamp,
+        attached_deposit: NearToken,
+    ) {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(predecessor)
+            .block_timestamp(timestamp)
+            .attached_deposit(attached_deposit)
+            .build());
+    }
+
+    /// Creates a contract owned by `accounts(0)` with `CODEHASH` approved.
+    pub(crate) fn setup() -> Contract {
+        set_context(accounts(0), ts(0));
+        let mut contract = Contract::init(accounts(0));
+        contract.approve_codehash(CODEHASH.to_string());
+        contract
+    }
+
+    pub(crate) fn add_test_oracle(contract: &mut Contract, account_id: &AccountId) {
+        let mut oracle = Oracle::new();
+        oracle.codehash = Some(CODEHASH.to_string());
+        contract.internal_add_oracle(account_id, oracle);
+        flush_test_state(contract);
+    }
+
+    /// Writes the cached collections, as the runtime does at the end of each call, so a later
+    /// context isn't charged for them.
+    pub(crate) fn flush_test_state(contract: &mut Contract) {
+        contract.oracles.flush();
+        contract.assets.flush();
+        contract.oracle_ids_by_index.flush();
+        contract.oracle_indices.flush();
+    }
+
+    pub(crate) fn add_test_asset(contract: &mut Contract, asset_id: &str) {
+        let timestamp = env::block_timestamp();
+        set_context_with_deposit(contract.owner_id.clone(), timestamp, NearToken::from_near(1));
+        contract.add_asset(asset_id.to_string());
+        set_context(contract.owner_id.clone(), timestamp);
+    }
+
+    pub(crate) fn asset_prices(prices: &[(&str, Price)]) -> Vec<AssetPrice> {
+        prices
+            .iter()
+            .map(|(asset_id, price)| AssetPrice {
+                asset_id: asset_id.to_string(),
+                price: *price,
+                observed_at: None,
+            })
+            .collect()
+    }
+
+    pub(crate) fn report(
+        contract: &mut Contract,
+        oracle_id: &AccountId,
+        timestamp: Timestamp,
+        prices: &[(&str, Price)],
+    ) {
+        set_context(oracle_id.clone(), timestamp);
+        contract.report_prices(asset_prices(prices), None, None);
+        flush_test_state(contract);
+    }
+
+    pub(crate) fn spot(contract: &Contract, asset_id: &str) -> Option<Price> {
+        contract
+            .get_price_data(Some(vec![asset_id.to_string()]), None, None)
+            .prices
+            .into_iter()
+            .next()
+            .and_then(|p| p.price)
+    }
+
+    #[test]
+    fn test_price_data_with_spread() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_oracle(&mut contract, &accounts(2));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(100, 0))]);
+        report(&mut contract, &accounts(2), ts(10), &[("wrap.near", price(100, 0))]);
+
+        let data = contract.get_price_data_with_spread(None);
+        assert_eq!(data.prices[0].min_price, Some(price(100, 0)));
+        assert_eq!(data.prices[0].max_price, Some(price(100, 0)));
+        assert_eq!(data.prices[0].num_reports, 2);
+
+        report(&mut contract, &accounts(2), ts(20), &[("wrap.near", price(1500, 1))]);
+        let data = contract.get_price_data_with_spread(None);
+        assert_eq!(data.prices[0].min_price, Some(price(100, 0)));
+        assert_eq!(data.prices[0].max_price, Some(price(1500, 1)));
+        assert_eq!(data.prices[0].num_reports, 2);
+
+        let data = contract.get_price_data_with_spread(Some(vec!["dai.near".to_string()]));
+        assert_eq!(data.prices[0].price, None);
+        assert_eq!(data.prices[0].num_reports, 0);
+    }
+
+    #[test]
+    fn test_price_data_degraded() {
+        let mut contract = setup();
+        for i in 1..=3 {
+            add_test_oracle(&mut contract, &accounts(i));
+        }
+        add_test_asset(&mut contract, "wrap.near");
+        add_test_asset(&mut contract, "dai.near");
+        add_test_asset(&mut contract, "usdc.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+        report(&mut contract, &accounts(1), ts(11), &[("dai.near", price(1, 0))]);
+        report(&mut contract, &accounts(2), ts(10), &[("dai.near", price(1, 0))]);
+
+        assert_eq!(spot(&contract, "wrap.near"), None);
+        let prices = contract.get_price_data_degraded(Some(vec![
+            "wrap.near".to_string(),
+            "dai.near".to_string(),
+            "usdc.near".to_string(),
+        ]));
+        assert_eq!(prices[0].price, Some(price(5, 0)));
+        assert!(prices[0].degraded);
+        assert_eq!(prices[0].num_fresh, 1);
+        assert_eq!(prices[1].price, Some(price(1, 0)));
+        assert!(!prices[1].degraded);
+        assert_eq!(prices[1].num_fresh, 2);
+        assert_eq!(prices[2].price, None);
+        assert!(!prices[2].degraded);
+        assert_eq!(prices[2].num_fresh, 0);
+    }
+
+    #[test]
+    fn test_price_data_degraded_alias() {
+        let mut contract = setup();
+        for i in 1..=3 {
+            add_test_oracle(&mut contract, &accounts(i));
+        }
+        add_test_asset(&mut contract, "wnear.near");
+        contract.set_asset_alias("wrap.near".to_string(), "wnear.near".to_string());
+        report(&mut contract, &accounts(1), ts(10), &[("wnear.near", price(5, 0))]);
+
+        let prices = contract.get_price_data_degraded(Some(vec!["wrap.near".to_string()]));
+        assert_eq!(prices[0].asset_id, "wrap.near");
+        assert_eq!(prices[0].price, Some(price(5, 0)));
+        assert!(prices[0].degraded);
+        assert_eq!(prices[0].num_fresh, 1);
+    }
+
+    #[test]
+    fn test_combined_price() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        contract.add_asset_ema("wrap.near".to_string(), 3600, None, None);
+        contract.add_asset_ema("wrap.near".to_string(), 60, None, None);
+        for (sec, multiplier) in [(10, 100), (20, 110), (30, 120)] {
+            report(&mut contract, &accounts(1), ts(sec), &[("wrap.near", price(multiplier, 0))]);
+        }
+
+        let combined = contract.get_combined_price("wrap.near".to_string());
+        assert_eq!(combined.spot, Some(price(120, 0)));
+        assert_eq!(combined.emas.len(), 2);
+        let ema = combined.emas.get(&3600).unwrap();
+        assert!(*ema > price(100, 0) && *ema < price(120, 0));
+        assert_eq!(combined.timestamp, Some(ts(30)));
+    }
+
+    #[test]
+    fn test_verify_price_data() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        add_test_asset(&mut contract, "dai.near");
+        report(
+            &mut contract,
+            &accounts(1),
+            ts(10),
+            &[("wrap.near", price(5, 0)), ("dai.near", price(1, 0))],
+        );
+
+        let data = contract.get_price_data(None, None, None);
+        let mut result = contract.verify_price_data(data);
+        result.sort();
+        assert_eq!(
+            result,
+            vec![("dai.near".to_string(), true), ("wrap.near".to_string(), true)]
+        );
+
+        let stale = contract.get_price_data(Some(vec!["wrap.near".to_string()]), None, None);
+        report(&mut contract, &accounts(1), ts(20), &[("wrap.near", price(6, 0))]);
+        assert_eq!(
+            contract.verify_price_data(stale),
+            vec![("wrap.near".to_string(), false)]
+        );
+    }
+
+    #[test]
+    fn test_price_data_cascade() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        add_test_asset(&mut contract, "dai.near");
+        report(&mut contract, &accounts(1), ts(10), &[("dai.near", price(1, 0))]);
+        report(&mut contract, &accounts(1), ts(3000), &[("wrap.near", price(5, 0))]);
+
+        set_context(accounts(0), ts(3100));
+        let prices = contract.get_price_data_cascade(None, vec![3600, 60, 600]);
+        let wrap = prices.iter().find(|p| p.asset_id == "wrap.near").unwrap();
+        assert_eq!(wrap.price, Some(price(5, 0)));
+        assert_eq!(wrap.recency_duration_sec, Some(600));
+        let dai = prices.iter().find(|p| p.asset_id == "dai.near").unwrap();
+        assert_eq!(dai.price, Some(price(1, 0)));
+        assert_eq!(dai.recency_duration_sec, Some(3600));
+
+        set_context(accounts(0), ts(10000));
+        let prices = contract.get_price_data_cascade(Some(vec!["wrap.near".to_string()]), vec![60]);
+        assert_eq!(prices[0].price, None);
+        assert_eq!(prices[0].recency_duration_sec, None);
+    }
+
+    #[test]
+    fn test_convert() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        let amount = U128(5 * 10u128.pow(24));
+        assert_eq!(contract.convert("wrap.near".to_string(), amount, 24, 18), None);
+
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(1000, 26))]);
+        assert_eq!(
+            contract.convert("wrap.near".to_string(), amount, 24, 18),
+            Some(U128(50 * 10u128.pow(18)))
+        );
+    }
+
+    #[test]
+    fn test_price_multi_decimals() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(123456, 8))]);
+
+        let prices = contract.get_price_multi_decimals("wrap.near".to_string(), vec![6, 18, 24, 60]);
+        let multipliers: Vec<_> = prices
+            .iter()
+            .map(|(decimals, price)| (*decimals, price.map(|p| (p.multiplier, p.decimals))))
+            .collect();
+        assert_eq!(
+            multipliers,
+            vec![
+                (6, Some((1234, 6))),
+                (18, Some((123456 * 10u128.pow(10), 18))),
+                (24, Some((123456 * 10u128.pow(16), 24))),
+                (60, None),
+            ]
+        );
+        assert_eq!(prices[1].1, prices[2].1);
+    }
+
+    #[test]
+    fn test_price_data_stats_skewed() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        for (i, multiplier) in [100, 101, 102, 400].into_iter().enumerate() {
+            let oracle_id = accounts(i + 1);
+            add_test_oracle(&mut contract, &oracle_id);
+            report(&mut contract, &oracle_id, ts(10), &[("wrap.near", price(multiplier, 0))]);
+        }
+
+        let stats = contract.get_price_data_stats(None);
+        assert_eq!(stats[0].median, Some(price(102, 0)));
+        assert_eq!(stats[0].mean, Some(price(175, 0)));
+        assert_eq!(stats[0].spread_bps, Some(29411));
+        assert_eq!(stats[0].num_fresh, 4);
+    }
+
+    #[test]
+    fn test_geometric_aggregation() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_oracle(&mut contract, &accounts(2));
+        add_test_oracle(&mut contract, &accounts(3));
+        add_test_asset(&mut contract, "index.near");
+        contract.set_asset_aggregation("index.near".to_string(), AggregationMethod::Geometric);
+        report(&mut contract, &accounts(1), ts(10), &[("index.near", price(1, 0))]);
+        report(&mut contract, &accounts(2), ts(10), &[("index.near", price(2, 0))]);
+        report(&mut contract, &accounts(3), ts(10), &[("index.near", price(32, 0))]);
+        assert_eq!(spot(&contract, "index.near"), Some(price(4, 0)));
+
+        set_context(accounts(0), ts(10));
+        contract.set_asset_aggregation("index.near".to_string(), AggregationMethod::Median);
+        assert_eq!(spot(&contract, "index.near"), Some(price(2, 0)));
+    }
+
+    #[test]
+    fn test_spot_serving_disabled() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        contract.add_asset_ema("wrap.near".to_string(), 3600, None, None);
+        contract.set_asset_spot_serving_disabled("wrap.near".to_string(), true);
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(100, 0))]);
+
+        let data = contract.get_price_data(
+            Some(vec!["wrap.near".to_string(), "wrap.near#3600".to_string()]),
+            None,
+            None,
+        );
+        assert_eq!(data.prices[0].price, None);
+        assert_eq!(data.prices[0].status, Some(PriceStatus::SpotDisabled));
+        assert_eq!(data.prices[1].price, Some(price(100, 0)));
+    }
+
+    #[test]
+    fn test_price_status_off_session() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "aapl");
+        // ts(0) is 12:26:40 UTC, so the session is 12:00 - 14:00 UTC.
+        contract.set_asset_sessions(
+            "aapl".to_string(),
+            vec![SessionWindow {
+                start_sec: 43200,
+                end_sec: 50400,
+            }],
+        );
+
+        report(&mut contract, &accounts(1), ts(10), &[("aapl", price(100, 0))]);
+        let data = contract.get_price_data(Some(vec!["aapl".to_string()]), None, None);
+        assert_eq!(data.prices[0].price, Some(price(100, 0)));
+        assert_eq!(data.prices[0].status, None);
+
+        report(&mut contract, &accounts(1), ts(7200), &[("aapl", price(101, 0))]);
+        let data = contract.get_price_data(Some(vec!["aapl".to_string()]), None, None);
+        assert_eq!(data.prices[0].price, Some(price(101, 0)));
+        assert_eq!(data.prices[0].status, Some(PriceStatus::OffSession));
+    }
+
+    #[test]
+    fn test_get_assets_by_prefix() {
+        let mut contract = setup();
+        for asset_id in ["eth.bridge.near", "wrap.near", "eth.rainbow.near", "usdc.bridge.near"] {
+            add_test_asset(&mut contract, asset_id);
+        }
+        let asset_ids = |prefix: &str, from_index, limit| -> Vec<AssetId> {
+            contract
+                .get_assets_by_prefix(prefix.to_string(), from_index, limit)
+                .into_iter()
+                .map(|(asset_id, _)| asset_id)
+                .collect()
+        };
+        assert_eq!(asset_ids("eth.", None, None), vec!["eth.bridge.near", "eth.rainbow.near"]);
+        assert_eq!(asset_ids("eth.", Some(1), Some(5)), vec!["eth.rainbow.near"]);
+        assert_eq!(asset_ids("eth.", Some(0), Some(1)), vec!["eth.bridge.near"]);
+        assert!(asset_ids("dai.", None, None).is_empty());
+    }
+
+    #[test]
+    fn test_get_assets_with_prices() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        add_test_asset(&mut contract, "aurora");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+
+        let summary: Vec<_> = contract
+            .get_assets_with_prices(None, None)
+            .into_iter()
+            .map(|(asset_id, asset, price)| (asset_id, asset.reports.len(), price))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("wrap.near".to_string(), 1, Some(price(5, 0))),
+                ("aurora".to_string(), 0, None)
+            ]
+        );
+        assert_eq!(contract.get_assets_with_prices(Some(1), Some(5)).len(), 1);
+    }
+
+    #[test]
+    fn test_supported_suffixes() {
+        let contract = setup();
+        let suffixes = contract.get_supported_suffixes();
+        assert!(suffixes.iter().any(|suffix| suffix.pattern == "#<period_sec>"));
+    }
+
+    #[test]
+    fn test_explain_asset_id() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        contract.add_asset_ema("wrap.near".to_string(), 3600, None, None);
+
+        let trace = contract.explain_asset_id("wrap.near#3600".to_string());
+        assert_eq!(trace.steps, vec!["EMA period 3600".to_string()]);
+        assert_eq!(trace.source_asset_ids, vec!["wrap.near".to_string()]);
+
+        let trace = contract.explain_asset_id("wrap.near".to_string());
+        assert_eq!(trace.steps, vec!["spot median".to_string()]);
+        assert_eq!(trace.source_asset_ids, vec!["wrap.near".to_string()]);
+
+        let trace = contract.explain_asset_id("dai.near".to_string());
+        assert_eq!(trace.steps.last().unwrap(), "unknown asset dai.near");
+        assert!(trace.source_asset_ids.is_empty());
+    }
+
+    #[test]
+    fn test_get_stale_oracles() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_oracle(&mut contract, &accounts(2));
+        add_test_oracle(&mut contract, &accounts(3));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(100), &[("wrap.near", price(1, 0))]);
+        report(&mut contract, &accounts(2), ts(160), &[("wrap.near", price(1, 0))]);
+
+        set_context(accounts(0), ts(200));
+        // accounts(2) reported exactly at the cutoff, so it's not stale.
+        let mut stale = contract.get_stale_oracles(40);
+        stale.sort();
+        assert_eq!(stale, vec![accounts(1), accounts(3)]);
+
+        assert_eq!(contract.get_stale_oracles(1000), vec![accounts(3)]);
+    }
+
+    #[test]
+    fn test_oracle_claim_amount_override() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_oracle(&mut contract, &accounts(2));
+        add_test_oracle(&mut contract, &accounts(3));
+        add_test_asset(&mut contract, "wrap.near");
+        contract.set_oracle_claim_amount(accounts(1), Some(U128(2 * 10u128.pow(24))));
+        contract.set_oracle_claim_amount(accounts(2), Some(U128(3 * 10u128.pow(23))));
+
+        for i in 1..=3 {
+            set_context(accounts(i), ts(10));
+            contract.report_prices(asset_prices(&[("wrap.near", price(5, 0))]), Some(true), None);
+        }
+        let claimed = |i| contract.get_oracle(accounts(i)).unwrap().total_near_claimed;
+        assert_eq!(claimed(1), NearToken::from_near(2));
+        assert_eq!(claimed(2), NearToken::from_millinear(300));
+        assert_eq!(claimed(3), contract.near_claim_amount);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_PRICE: Price decimals can't exceed 38 for this asset")]
+    fn test_report_high_decimals_rejected() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(1, 50))]);
+    }
+
+    #[test]
+    fn test_report_high_decimals_allowed() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        contract.set_asset_allow_high_decimals("wrap.near".to_string(), true);
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(1, 50))]);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(1, 50)));
+    }
+
+    #[test]
+    fn test_report_prices_lenient_skips_unknown_asset() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        set_context(accounts(1), ts(10));
+        contract.report_prices(
+            asset_prices(&[("wrap.near", price(100, 2)), ("missing.near", price(5, 0))]),
+            None,
+            Some(false),
+        );
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(100, 2)));
+        assert!(contract.get_asset("missing.near".to_string()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "E_UNKNOWN_ASSET: Unknown asset ID: missing.near")]
+    fn test_report_prices_strict_rejects_unknown_asset() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        set_context(accounts(1), ts(10));
+        contract.report_prices(
+            asset_prices(&[("wrap.near", price(100, 2)), ("missing.near", price(5, 0))]),
+            None,
+            Some(true),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "E_REPORT_INTERVAL: Oracle reports too frequently, the minimum interval is 60 seconds")]
+    fn test_min_report_interval() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        contract.set_min_report_interval_sec(60);
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+        report(&mut contract, &accounts(1), ts(70), &[("wrap.near", price(5, 0))]);
+        report(&mut contract, &accounts(1), ts(100), &[("wrap.near", price(5, 0))]);
+    }
+
+    #[test]
+    fn test_touch_emas() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        contract.add_asset_ema("wrap.near".to_string(), 600, None, None);
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+        let ema = |contract: &Contract| contract.get_asset("wrap.near".to_string()).unwrap().emas[0].clone();
+        assert_eq!(ema(&contract).timestamp, ts(10));
+
+        // Already updated at this timestamp, so the value isn't recomputed.
+        set_context(accounts(2), ts(10));
+        contract.touch_emas(vec!["wrap.near".to_string()]);
+        assert_eq!(ema(&contract).price.unwrap().decimals, 0);
+
+        set_context(accounts(2), ts(1000));
+        contract.touch_emas(vec!["wrap.near".to_string()]);
+        assert_eq!(ema(&contract).timestamp, ts(1000));
+        assert_eq!(ema(&contract).price, Some(price(5, 0)));
+
+        // No fresh median anymore, so the EMA is left as is.
+        set_context(accounts(2), ts(5000));
+        contract.touch_emas(vec!["wrap.near".to_string()]);
+        assert_eq!(ema(&contract).timestamp, ts(1000));
+    }
+
+    #[test]
+    fn test_asset_recency_duration_override() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        add_test_asset(&mut contract, "usdc.near");
+        contract.set_asset_recency_duration_sec("wrap.near".to_string(), Some(60));
+        contract.set_asset_recency_duration_sec("usdc.near".to_string(), Some(7200));
+        report(
+            &mut contract,
+            &accounts(1),
+            ts(10),
+            &[("wrap.near", price(5, 0)), ("usdc.near", price(1, 0))],
+        );
+
+        set_context(accounts(0), ts(60));
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(5, 0)));
+        set_context(accounts(0), ts(600));
+        assert_eq!(spot(&contract, "wrap.near"), None);
+        set_context(accounts(0), ts(5000));
+        assert_eq!(spot(&contract, "usdc.near"), Some(price(1, 0)));
+        let usdc = || Some(vec!["usdc.near".to_string()]);
+        assert_eq!(contract.get_combined_price("usdc.near".to_string()).spot, Some(price(1, 0)));
+        assert_eq!(contract.get_price_data_degraded(usdc())[0].price, Some(price(1, 0)));
+        assert_eq!(contract.get_price_data_with_spread(usdc()).prices[0].num_reports, 1);
+        assert_eq!(contract.get_price_data_stats(usdc())[0].median, Some(price(1, 0)));
+        assert_eq!(
+            contract.convert("usdc.near".to_string(), U128(3), 0, 0),
+            Some(U128(3))
+        );
+        assert_eq!(
+            contract.get_price_multi_decimals("usdc.near".to_string(), vec![2]),
+            vec![(2, Some(price(100, 2)))]
+        );
+        let data = contract.get_price_data(usdc(), None, None);
+        assert_eq!(contract.verify_price_data(data), vec![("usdc.near".to_string(), true)]);
+        assert!(!contract.get_oracle_stats(None, None)[0].is_stale);
+
+        contract.set_asset_recency_duration_sec("usdc.near".to_string(), None);
+        assert_eq!(spot(&contract, "usdc.near"), None);
+    }
+
+    #[test]
+    fn test_oracle_call_batch() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+
+        set_context(accounts(2), ts(20));
+        contract.oracle_call_batch(
+            vec![(accounts(3), "first".to_string()), (accounts(4), "second".to_string())],
+            None,
+            None,
+        );
+        let (callbacks, receipts): (Vec<_>, Vec<_>) = near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .partition(|receipt| receipt.receiver_id == env::current_account_id());
+        let receiver_ids: Vec<AccountId> =
+            receipts.into_iter().map(|receipt| receipt.receiver_id).collect();
+        assert_eq!(receiver_ids, vec![accounts(3), accounts(4)]);
+        assert_eq!(callbacks.len(), 1);
+        assert_eq!(callbacks[0].receipt_indices.len(), 2);
+        match &callbacks[0].actions[0] {
+            MockAction::FunctionCallWeight {
+                method_name, args, ..
+            } => {
+                assert_eq!(method_name, b"on_oracle_call_batch_complete");
+                let args: serde_json::Value = serde_json::from_slice(args).unwrap();
+                assert_eq!(args["receiver_ids"], serde_json::json!([accounts(3), accounts(4)]));
+            }
+            _ => panic!("Expected a function call"),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "The msg is too long, the maximum is 16 bytes")]
+    fn test_oracle_call_msg_too_long() {
+        let mut contract = setup();
+        contract.set_max_oracle_call_msg_len(16);
+        set_context(accounts(2), ts(20));
+        contract.oracle_call(accounts(3), None, "x".repeat(17), None);
+    }
+
+    #[test]
+    fn test_on_oracle_call_complete() {
+        let contract = setup();
+        for (result, success) in [
+            (PromiseResult::Successful(vec![]), true),
+            (PromiseResult::Failed, false),
+        ] {
+            testing_env!(
+                VMContextBuilder::new()
+                    .predecessor_account_id(accounts(0))
+                    .current_account_id(accounts(0))
+                    .build(),
+                near_sdk::test_vm_config(),
+                near_sdk::RuntimeFeesConfig::test(),
+                Default::default(),
+                vec![result],
+            );
+            assert_eq!(contract.on_oracle_call_complete(accounts(3)), success);
+        }
+    }
+
+    #[test]
+    fn test_on_oracle_call_batch_complete() {
+        let contract = setup();
+        testing_env!(
+            VMContextBuilder::new()
+                .predecessor_account_id(accounts(0))
+                .current_account_id(accounts(0))
+                .build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed, PromiseResult::Successful(vec![])],
+        );
+        assert_eq!(
+            contract.on_oracle_call_batch_complete(vec![accounts(3), accounts(4)]),
+            vec![false, true]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough gas for 100 receivers")]
+    fn test_oracle_call_batch_not_enough_gas() {
+        let mut contract = setup();
+        let receivers = (0..100).map(|i| (accounts(i % 6), i.to_string())).collect();
+        contract.oracle_call_batch(receivers, None, None);
+    }
+
+    #[test]
+    fn test_asset_disabled() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        contract.add_asset_ema("wrap.near".to_string(), 3600, None, None);
+        contract.set_asset_disabled("wrap.near".to_string(), true);
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+
+        let data = contract.get_price_data(
+            Some(vec!["wrap.near".to_string(), "wrap.near#3600".to_string()]),
+            None,
+            None,
+        );
+        for price in data.prices {
+            assert_eq!(price.price, None);
+            assert_eq!(price.status, Some(PriceStatus::Disabled));
+        }
+
+        set_context(accounts(0), ts(20));
+        contract.set_asset_disabled("wrap.near".to_string(), false);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(5, 0)));
+        assert_eq!(spot(&contract, "wrap.near#3600"), Some(price(5, 0)));
+    }
+
+    #[test]
+    fn test_price_data_freshness() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        add_test_asset(&mut contract, "dai.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+        report(&mut contract, &accounts(1), ts(4000), &[("dai.near", price(1, 0))]);
+
+        set_context(accounts(0), ts(4000));
+        let freshness: Vec<_> = contract
+            .get_price_data_freshness(Some(vec![
+                "wrap.near".to_string(),
+                "dai.near".to_string(),
+                "missing.near".to_string(),
+            ]))
+            .into_iter()
+            .map(|p| p.freshness)
+            .collect();
+        assert_eq!(
+            freshness,
+            vec![
+                PriceFreshness::Stale(price(5, 0), ts(10)),
+                PriceFreshness::Fresh(price(1, 0)),
+                PriceFreshness::Unknown,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ema_guard() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        contract.add_asset_ema("wrap.near".to_string(), 3600, None, None);
+        contract.set_asset_ema_guard("wrap.near".to_string(), 3600, 1000);
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(100, 0))]);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(100, 0)));
+
+        report(&mut contract, &accounts(1), ts(20), &[("wrap.near", price(200, 0))]);
+        let data = contract.get_price_data(Some(vec!["wrap.near".to_string()]), None, None);
+        assert_eq!(data.prices[0].price, None);
+        assert_eq!(data.prices[0].status, Some(PriceStatus::EmaDeviation));
+
+        report(&mut contract, &accounts(1), ts(30), &[("wrap.near", price(105, 0))]);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(105, 0)));
+    }
+
+    fn report_observed(contract: &mut Contract, timestamp: Timestamp, observed_at: Timestamp) {
+        set_context(accounts(1), timestamp);
+        let prices = vec![AssetPrice {
+            asset_id: "wrap.near".to_string(),
+            price: price(5, 0),
+            observed_at: Some(observed_at),
+        }];
+        contract.report_prices(prices, None, None);
+    }
+
+    #[test]
+    fn test_report_observed_at() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report_observed(&mut contract, ts(100), ts(40));
+        let asset = contract.get_asset("wrap.near".to_string()).unwrap();
+        assert_eq!(asset.reports[0].timestamp, ts(40));
+        assert_eq!(contract.get_oracle(accounts(1)).unwrap().last_report, ts(100));
+
+        set_context(accounts(0), ts(3640));
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(5, 0)));
+        set_context(accounts(0), ts(3641));
+        assert_eq!(spot(&contract, "wrap.near"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_OBSERVED_AT: Observation time of wrap.near is outside of the accepted window")]
+    fn test_report_observed_at_in_future() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report_observed(&mut contract, ts(100), ts(101));
+    }
+
+    #[test]
+    #[should_panic(expected = "E_OBSERVED_AT: Observation time of wrap.near is outside of the accepted window")]
+    fn test_report_observed_at_too_old() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report_observed(&mut contract, ts(4000), ts(399));
+    }
+
+    #[test]
+    fn test_report_negative_price_for_signed_asset() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "funding.near");
+        contract.set_asset_signed("funding.near".to_string(), true);
+        let negative = Price {
+            negative: true,
+            ..price(5, 4)
+        };
+        report(&mut contract, &accounts(1), ts(10), &[("funding.near", negative)]);
+        let served = spot(&contract, "funding.near").unwrap();
+        assert!(served.negative);
+        assert_eq!(served, negative);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_PRICE: Negative prices are not allowed for this asset")]
+    fn test_report_negative_price_for_unsigned_asset() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        let negative = Price {
+            negative: true,
+            ..price(5, 4)
+        };
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", negative)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_PRICE: Zero prices are not allowed for this asset")]
+    fn test_report_zero_price_rejected() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(0, 4))]);
+    }
+
+    #[test]
+    fn test_report_zero_price_allowed() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "spread.near");
+        contract.set_asset_allow_zero_price("spread.near".to_string(), true);
+        report(&mut contract, &accounts(1), ts(10), &[("spread.near", price(0, 4))]);
+        assert!(spot(&contract, "spread.near").unwrap().is_zero());
+    }
+
+    fn set_test_validation(contract: &mut Contract, flags: u32, params: AssetValidationParams) {
+        set_context(accounts(0), ts(0));
+        contract.set_asset_validation("wrap.near".to_string(), flags, Some(params));
+    }
+
+    fn bounds_and_decimals() -> AssetValidationParams {
+        AssetValidationParams {
+            min_price: Some(price(1, 0)),
+            max_price: Some(price(10, 0)),
+            decimals: Some(0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validation_skips_disabled_checks() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        set_test_validation(&mut contract, VALIDATE_DECIMALS, bounds_and_decimals());
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(20, 0))]);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(20, 0)));
+
+        set_test_validation(&mut contract, VALIDATE_BOUNDS, bounds_and_decimals());
+        report(&mut contract, &accounts(1), ts(20), &[("wrap.near", price(50, 1))]);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(5, 0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "E_VALIDATION: Price is above the asset maximum")]
+    fn test_validation_bounds() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        set_test_validation(&mut contract, VALIDATE_BOUNDS, bounds_and_decimals());
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(20, 0))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_VALIDATION: Price decimals don't match the asset")]
+    fn test_validation_decimals() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        set_test_validation(&mut contract, VALIDATE_DECIMALS, bounds_and_decimals());
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(50, 1))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_DEVIATION: Price deviates too much from the median")]
+    fn test_validation_deviation() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_oracle(&mut contract, &accounts(2));
+        add_test_asset(&mut contract, "wrap.near");
+        let params = AssetValidationParams {
+            max_deviation_bps: Some(1000),
+            ..Default::default()
+        };
+        set_test_validation(&mut contract, VALIDATE_DEVIATION, params);
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(100, 0))]);
+        report(&mut contract, &accounts(2), ts(10), &[("wrap.near", price(105, 0))]);
+        report(&mut contract, &accounts(2), ts(20), &[("wrap.near", price(200, 0))]);
+    }
+
+    #[test]
+    fn test_validation_min_reporters() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_oracle(&mut contract, &accounts(2));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(100, 0))]);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(100, 0)));
+
+        let params = AssetValidationParams {
+            min_reporters: Some(2),
+            ..Default::default()
+        };
+        set_test_validation(&mut contract, VALIDATE_MIN_REPORTERS, params);
+        assert_eq!(spot(&contract, "wrap.near"), None);
+        report(&mut contract, &accounts(2), ts(10), &[("wrap.near", price(100, 0))]);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(100, 0)));
+    }
+
+    #[test]
+    fn test_get_asset_reports() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_oracle(&mut contract, &accounts(2));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(100, 0))]);
+        report(&mut contract, &accounts(2), ts(20), &[("wrap.near", price(110, 0))]);
+
+        let reports = contract.get_asset_reports("wrap.near".to_string(), None);
+        let summary: Vec<_> = reports
+            .iter()
+            .map(|rp| (rp.oracle_id.clone(), rp.timestamp, rp.price))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (accounts(1), ts(10), price(100, 0)),
+                (accounts(2), ts(20), price(110, 0))
+            ]
+        );
+
+        set_context(accounts(0), ts(3611));
+        let reports = contract.get_asset_reports("wrap.near".to_string(), None);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].oracle_id, accounts(2));
+        let reports = contract.get_asset_reports("wrap.near".to_string(), Some(true));
+        assert_eq!(reports.len(), 2);
+        assert!(contract.get_asset_reports("dai.near".to_string(), None).is_empty());
+    }
+
+    fn add_bounded_test_asset(contract: &mut Contract, asset_id: &str) {
+        add_test_asset(contract, asset_id);
+        contract.set_asset_price_bounds(
+            asset_id.to_string(),
+            Some(price(50, 0)),
+            Some(price(200, 0)),
+        );
+    }
+
+    #[test]
+    fn test_report_out_of_bounds_skipped() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_bounded_test_asset(&mut contract, "wrap.near");
+        add_test_asset(&mut contract, "dai.near");
+        report(
+            &mut contract,
+            &accounts(1),
+            ts(10),
+            &[("wrap.near", price(100, 0)), ("dai.near", price(1, 0))],
+        );
+        report(
+            &mut contract,
+            &accounts(1),
+            ts(20),
+            &[("wrap.near", price(100_000, 0)), ("dai.near", price(2, 0))],
+        );
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(100, 0)));
+        assert_eq!(spot(&contract, "dai.near"), Some(price(2, 0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "E_PRICE_BOUNDS: Price of wrap.near is outside of the asset bounds")]
+    fn test_report_out_of_bounds_strict_rejected() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_bounded_test_asset(&mut contract, "wrap.near");
+        set_context(accounts(1), ts(10));
+        contract.report_prices(
+            asset_prices(&[("wrap.near", price(49, 0))]),
+            None,
+            Some(true),
+        );
+    }
+
+    #[test]
+    fn test_price_data_median_age() {
+        let mut contract = setup();
+        for i in 1..=3 {
+            add_test_oracle(&mut contract, &accounts(i));
+        }
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(90, 0))]);
+        report(&mut contract, &accounts(2), ts(20), &[("wrap.near", price(100, 0))]);
+        report(&mut contract, &accounts(3), ts(30), &[("wrap.near", price(110, 0))]);
+
+        set_context(accounts(0), ts(100));
+        let ages = contract.get_price_data_median_age(Some(vec![
+            "wrap.near".to_string(),
+            "dai.near".to_string(),
+        ]));
+        assert_eq!(ages[0].median_age_sec, Some(80));
+        assert_eq!(ages[0].max_age_sec, Some(90));
+        assert_eq!(ages[1].median_age_sec, None);
+        assert_eq!(ages[1].max_age_sec, None);
+    }
+
+    /// Returns the gas attached to the first function call created by the last call.
+    fn forwarded_gas() -> Gas {
+        let receipts = near_sdk::test_utils::get_created_receipts();
+        match &receipts[0].actions[0] {
+            MockAction::FunctionCallWeight { prepaid_gas, .. } => *prepaid_gas,
+            _ => panic!("Expected a function call"),
+        }
+    }
+
+    #[test]
+    fn test_oracle_call_forwards_price_data() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+
+        set_context(accounts(2), ts(20));
+        contract.oracle_call(accounts(3), None, "msg".to_string(), None);
+        let receipts = near_sdk::test_utils::get_created_receipts();
+        let args = match &receipts[0].actions[0] {
+            MockAction::FunctionCallWeight { args, .. } => args.clone(),
+            _ => panic!("Expected a function call"),
+        };
+        let expected = (
+            accounts(2),
+            contract.get_price_data(None, None, None),
+            "msg".to_string(),
+        );
+        assert_eq!(args, serde_json::to_vec(&expected).unwrap());
+    }
+
+    #[test]
+    fn test_oracle_call_chains_callback() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+
+        set_context(accounts(2), ts(20));
+        contract.oracle_call(accounts(3), None, "msg".to_string(), None);
+        let receipts = near_sdk::test_utils::get_created_receipts();
+        assert_eq!(receipts.len(), 2);
+        assert_eq!(receipts[1].receiver_id, env::current_account_id());
+        assert_eq!(receipts[1].receipt_indices, vec![0]);
+        match &receipts[1].actions[0] {
+            MockAction::FunctionCallWeight {
+                method_name, args, ..
+            } => {
+                assert_eq!(method_name, b"on_oracle_call_complete");
+                let args: serde_json::Value = serde_json::from_slice(args).unwrap();
+                assert_eq!(args["receiver_id"], accounts(3).to_string());
+            }
+            _ => panic!("Expected a function call"),
+        }
+    }
+
+    #[test]
+    fn test_oracle_call_callback_gas() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+
+        for (callback_gas, reserved_gas) in [
+            (None, GAS_FOR_PROMISE),
+            (Some(Gas::from_tgas(50)), Gas::from_tgas(50)),
+            (Some(Gas::from_tgas(250)), MAX_GAS_FOR_PROMISE),
+        ] {
+            set_context(accounts(2), ts(20));
+            contract.oracle_call(accounts(3), None, "msg".to_string(), callback_gas);
+            let reserved_gas = reserved_gas.as_gas()
+                + GAS_FOR_ON_ORACLE_CALL_COMPLETE.as_gas()
+                + GAS_FOR_CALLBACK_RECEIPT.as_gas();
+            let forwarded_gas = forwarded_gas().as_gas();
+            assert!(forwarded_gas + reserved_gas <= env::prepaid_gas().as_gas());
+            assert!(
+                forwarded_gas + reserved_gas + env::used_gas().as_gas()
+                    >= env::prepaid_gas().as_gas()
+            );
+        }
+    }
+
+    #[test]
+    fn test_oracle_call_batch_callback_gas() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+
+        set_context(accounts(2), ts(20));
+        contract.oracle_call_batch(
+            vec![(accounts(3), "first".to_string()), (accounts(4), "second".to_string())],
+            None,
+            Some(Gas::from_tgas(50)),
+        );
+        let reserved_gas = Gas::from_tgas(50).as_gas()
+            + GAS_FOR_ON_ORACLE_CALL_COMPLETE.as_gas()
+            + 2 * GAS_FOR_CALLBACK_RECEIPT.as_gas();
+        assert!(2 * forwarded_gas().as_gas() + reserved_gas <= env::prepaid_gas().as_gas());
+    }
+
+    #[test]
+    fn test_get_status() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_oracle(&mut contract, &accounts(2));
+        add_test_asset(&mut contract, "wrap.near");
+
+        let status = contract.get_status();
+        assert_eq!(status.owner_id, accounts(0));
+        assert_eq!(status.num_oracles, 2);
+        assert_eq!(status.num_assets, 1);
+        assert_eq!(status.recency_duration_sec, contract.recency_duration_sec);
+        assert_eq!(status.near_claim_amount, contract.get_near_claim_amount());
+        assert_eq!(status.version, env!("CARGO_PKG_VERSION"));
+    }
+
+    #[test]
+    fn test_inactive_oracle_excluded_from_median() {
+        let mut contract = setup();
+        for i in 1..=3 {
+            add_test_oracle(&mut contract, &accounts(i));
+        }
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(90, 0))]);
+        report(&mut contract, &accounts(2), ts(10), &[("wrap.near", price(100, 0))]);
+        report(&mut contract, &accounts(3), ts(10), &[("wrap.near", price(110, 0))]);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(100, 0)));
+
+        set_context(accounts(0), ts(20));
+        contract.set_oracle_active(accounts(2), false);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(110, 0)));
+        assert_eq!(contract.get_asset("wrap.near".to_string()).unwrap().reports.len(), 3);
+
+        contract.set_oracle_active(accounts(2), true);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(100, 0)));
+    }
+
+    #[test]
+    fn test_inactive_oracle_excluded_from_stats() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        for (i, multiplier) in [100, 101, 102, 400].into_iter().enumerate() {
+            let oracle_id = accounts(i + 1);
+            add_test_oracle(&mut contract, &oracle_id);
+            report(&mut contract, &oracle_id, ts(10), &[("wrap.near", price(multiplier, 0))]);
+        }
+
+        set_context(accounts(0), ts(20));
+        contract.set_oracle_active(accounts(4), false);
+        let stats = contract.get_price_data_stats(None);
+        assert_eq!(stats[0].median, Some(price(101, 0)));
+        assert_eq!(stats[0].mean, Some(price(101, 0)));
+        assert_eq!(stats[0].spread_bps, Some(198));
+        assert_eq!(stats[0].num_fresh, 3);
+
+        let data = contract.get_price_data_with_spread(None);
+        assert_eq!(data.prices[0].max_price, Some(price(102, 0)));
+        assert_eq!(data.prices[0].num_reports, 3);
+        assert_eq!(contract.get_price_data_degraded(None)[0].num_fresh, 3);
+    }
+
+    #[test]
+    fn test_inactive_oracle_excluded_from_quorum() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        for i in 1..=3 {
+            add_test_oracle(&mut contract, &accounts(i));
+        }
+        assert_eq!(contract.internal_min_num_recent_reports(), 2);
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(100, 0))]);
+        assert_eq!(spot(&contract, "wrap.near"), None);
+
+        set_context(accounts(0), ts(20));
+        contract.set_oracle_active(accounts(3), false);
+        assert_eq!(contract.internal_min_num_recent_reports(), 1);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(100, 0)));
+    }
+
+    #[test]
+    fn test_get_asset_oracle_prices() {
+        let mut contract = setup();
+        for i in 1..=3 {
+            add_test_oracle(&mut contract, &accounts(i));
+        }
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(90, 0))]);
+        report(&mut contract, &accounts(2), ts(20), &[("wrap.near", price(100, 0))]);
+        report(&mut contract, &accounts(3), ts(30), &[("wrap.near", price(110, 0))]);
+
+        set_context(accounts(0), ts(40));
+        assert_eq!(
+            contract.get_asset_oracle_prices("wrap.near".to_string(), None),
+            vec![
+                (accounts(1), Some(price(90, 0))),
+                (accounts(2), Some(price(100, 0))),
+                (accounts(3), Some(price(110, 0))),
+            ]
+        );
+        assert_eq!(
+            contract.get_asset_oracle_prices("wrap.near".to_string(), Some(25)),
+            vec![
+                (accounts(1), None),
+                (accounts(2), Some(price(100, 0))),
+                (accounts(3), Some(price(110, 0))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ema_seed_from_spot() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "seeded.near");
+        add_test_asset(&mut contract, "unseeded.near");
+        let prices = |p| [("seeded.near", p), ("unseeded.near", p)];
+        report(&mut contract, &accounts(1), ts(10), &prices(price(100, 0)));
+
+        set_context(accounts(0), ts(20));
+        contract.add_asset_ema("seeded.near".to_string(), 600, None, Some(true));
+        contract.add_asset_ema("unseeded.near".to_string(), 600, None, None);
+        assert_eq!(spot(&contract, "seeded.near#600"), Some(price(100, 0)));
+        assert_eq!(spot(&contract, "unseeded.near#600"), None);
+
+        report(&mut contract, &accounts(1), ts(80), &prices(price(200, 0)));
+        let seeded = spot(&contract, "seeded.near#600").unwrap();
+        let as_f64 = |p: Price| p.multiplier as f64 / 10f64.powi(p.decimals as i32);
+        assert!(as_f64(seeded) > 100.0 && as_f64(seeded) < 200.0);
+        assert_eq!(spot(&contract, "unseeded.near#600"), Some(price(200, 0)));
+    }
+
+    #[test]
+    fn test_literal_hash_asset_id() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        add_test_asset(&mut contract, "wrap.near#1");
+        contract.add_asset_ema("wrap.near".to_string(), 3600, None, None);
+        report(
+            &mut contract,
+            &accounts(1),
+            ts(10),
+            &[("wrap.near", price(5, 0)), ("wrap.near#1", price(7, 0))],
+        );
+
+        assert_eq!(spot(&contract, "wrap.near#1"), Some(price(7, 0)));
+        assert_eq!(spot(&contract, "wrap.near#3600"), Some(price(5, 0)));
+        let trace = contract.explain_asset_id("wrap.near#1".to_string());
+        assert_eq!(trace.steps, vec!["spot median".to_string()]);
+        assert_eq!(trace.source_asset_ids, vec!["wrap.near#1".to_string()]);
+    }
+
+    #[test]
+    fn test_malformed_ema_period() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        contract.add_asset_ema("wrap.near".to_string(), 3600, None, None);
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+
+        for asset_id in ["wrap.near#abc", "wrap.near#", "wrap.near#0", "wrap.near#600"] {
+            let data = contract.get_price_data(Some(vec![asset_id.to_string()]), None, None);
+            assert_eq!(data.prices[0].asset_id, asset_id);
+            assert_eq!(data.prices[0].price, None);
+        }
+    }
+
+    #[test]
+    fn test_price_data_multi() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        add_test_asset(&mut contract, "dai.near");
+        report(
+            &mut contract,
+            &accounts(1),
+            ts(10),
+            &[("wrap.near", price(5, 0)), ("dai.near", price(1, 0))],
+        );
+
+        set_context(accounts(0), ts(100));
+        let query = |asset_id: &str, recency_duration_sec| PriceQuery {
+            asset_id: asset_id.to_string(),
+            recency_duration_sec,
+        };
+        let data = contract.get_price_data_multi(vec![
+            query("wrap.near", Some(60)),
+            query("dai.near", Some(600)),
+            query("wrap.near", None),
+        ]);
+        assert_eq!(data.timestamp, ts(100));
+        assert_eq!(data.prices[0].price, None);
+        assert_eq!(data.prices[0].recency_duration_sec, 60);
+        assert_eq!(data.prices[1].price, Some(price(1, 0)));
+        assert_eq!(data.prices[1].recency_duration_sec, 600);
+        assert_eq!(data.prices[2].price, Some(price(5, 0)));
+        assert_eq!(data.prices[2].recency_duration_sec, contract.recency_duration_sec);
+    }
+
+    #[test]
+    fn test_report_prunes_ancient_reports() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_oracle(&mut contract, &accounts(2));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+        report(&mut contract, &accounts(2), ts(100_000), &[("wrap.near", price(6, 0))]);
+        let num_reports = |contract: &Contract| {
+            contract
+                .get_asset_reports("wrap.near".to_string(), Some(true))
+                .len()
+        };
+        assert_eq!(num_reports(&contract), 2);
+
+        set_context(accounts(0), ts(100_000));
+        contract.set_report_prune_threshold_sec(Some(86400));
+        report(&mut contract, &accounts(2), ts(100_010), &[("wrap.near", price(6, 0))]);
+        let reports = contract.get_asset_reports("wrap.near".to_string(), Some(true));
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].oracle_id, accounts(2));
+    }
+
+    #[test]
+    fn test_get_percentile_price() {
+        let mut contract = setup();
+        for i in 1..=4 {
+            add_test_oracle(&mut contract, &accounts(i));
+        }
+        add_test_asset(&mut contract, "wrap.near");
+        for (i, multiplier) in [(1, 90), (2, 100), (3, 110), (4, 120)] {
+            report(&mut contract, &accounts(i), ts(10), &[("wrap.near", price(multiplier, 0))]);
+        }
+
+        let percentile = |p| contract.get_percentile_price("wrap.near".to_string(), p);
+        assert_eq!(percentile(1), Some(price(90, 0)));
+        assert_eq!(percentile(25), Some(price(100, 0)));
+        assert_eq!(percentile(50), spot(&contract, "wrap.near"));
+        assert_eq!(percentile(100), Some(price(120, 0)));
+        assert_eq!(contract.get_percentile_price("dai.near".to_string(), 50), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Percentile must be within [1, 100]")]
+    fn test_get_percentile_price_zero() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        contract.get_percentile_price("wrap.near".to_string(), 0);
+    }
+
+    #[test]
+    fn test_near_claim_skipped_on_low_balance() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        assert!(contract.can_claim(accounts(1)));
+
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(ts(10))
+            .account_balance(NearToken::from_millinear(500))
+            .build());
+        assert!(!contract.can_claim(accounts(1)));
+        contract.report_prices(asset_prices(&[("wrap.near", price(5, 0))]), Some(true), None);
+        assert!(near_sdk::test_utils::get_created_receipts().is_empty());
+        assert!(near_sdk::test_utils::get_logs()[0].starts_with("EVENT_JSON:"));
+        assert!(near_sdk::test_utils::get_logs()[0].contains("near_claim_skipped"));
+        assert_eq!(
+            contract.get_oracle(accounts(1)).unwrap().total_near_claimed,
+            NearToken::from_yoctonear(0)
+        );
+        assert!(!contract.can_claim(accounts(2)));
+    }
+
+    #[test]
+    fn test_near_claim_cooldown() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        let claim = |contract: &mut Contract, timestamp| {
+            set_context(accounts(1), timestamp);
+            contract.report_prices(asset_prices(&[("wrap.near", price(5, 0))]), Some(true), None);
+            contract.get_oracle(accounts(1)).unwrap().total_near_claimed
+        };
+        assert_eq!(claim(&mut contract, ts(10)), contract.near_claim_amount);
+        assert_eq!(claim(&mut contract, ts(3610)), contract.near_claim_amount);
+
+        set_context(accounts(0), ts(3610));
+        contract.set_near_claim_cooldown(3600);
+        assert_eq!(contract.get_near_claim_cooldown(), 3600);
+        assert_eq!(
+            claim(&mut contract, ts(3620)),
+            contract.near_claim_amount.saturating_mul(2)
+        );
+    }
+
+    #[test]
+    fn test_claim_safety_margin() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        assert_eq!(
+            contract.get_claim_safety_margin(),
+            U128(DEFAULT_CLAIM_SAFETY_MARGIN.as_yoctonear())
+        );
+        assert!(contract.internal_can_pay_near_claim(contract.near_claim_amount));
+
+        contract.set_claim_safety_margin(U128(env::account_balance().as_yoctonear()));
+        set_context(accounts(1), ts(10));
+        contract.report_prices(asset_prices(&[("wrap.near", price(5, 0))]), Some(true), None);
+        assert!(contract
+            .get_oracle(accounts(1))
+            .unwrap()
+            .total_near_claimed
+            .is_zero());
+    }
+
+    #[test]
+    fn test_asset_timestamps() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        set_context(accounts(0), ts(5));
+        add_test_asset(&mut contract, "wrap.near");
+        let asset = contract.get_asset("wrap.near".to_string()).unwrap();
+        assert_eq!((asset.created_at, asset.last_updated), (ts(5), 0));
+
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+        let asset = contract.get_asset("wrap.near".to_string()).unwrap();
+        assert_eq!((asset.created_at, asset.last_updated), (ts(5), ts(10)));
+        report(&mut contract, &accounts(1), ts(20), &[("wrap.near", price(6, 0))]);
+        let asset = contract.get_asset("wrap.near".to_string()).unwrap();
+        assert_eq!((asset.created_at, asset.last_updated), (ts(5), ts(20)));
+    }
+
+    #[test]
+    fn test_get_price_data_pagination() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        let asset_ids: Vec<String> = (0..5).map(|i| format!("token{}.near", i)).collect();
+        for asset_id in asset_ids.iter() {
+            add_test_asset(&mut contract, asset_id);
+        }
+        contract.set_max_price_data_assets(2);
+
+        let mut paged_ids = vec![];
+        for from_index in (0..5).step_by(2) {
+            let data = contract.get_price_data(None, Some(from_index), Some(2));
+            assert!(data.prices.len() <= 2);
+            paged_ids.extend(data.prices.into_iter().map(|asset_price| asset_price.asset_id));
+        }
+        assert_eq!(paged_ids, asset_ids);
+        assert_eq!(
+            contract.get_price_data(None, Some(4), None).prices[0].asset_id,
+            "token4.near"
+        );
+        // Explicit asset IDs aren't paginated.
+        assert_eq!(
+            contract
+                .get_price_data(Some(asset_ids.clone()), None, None)
+                .prices
+                .len(),
+            5
+        );
+    }
+
+    #[test]
+    fn test_price_data_views_capped() {
+        let mut contract = setup();
+        for i in 0..3 {
+            add_test_asset(&mut contract, &format!("token{}.near", i));
+        }
+        contract.set_max_price_data_assets(2);
+        assert_eq!(contract.get_price_data(None, None, None).prices.len(), 2);
+        assert_eq!(contract.get_price_data(None, None, Some(3)).prices.len(), 2);
+        assert_eq!(contract.get_price_data_stats(None).len(), 2);
+        assert_eq!(contract.get_price_data_with_spread(None).prices.len(), 2);
+        assert_eq!(contract.get_price_data_freshness(None).len(), 2);
+    }
+
+    #[test]
+    fn test_get_cross_price() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "eth.near");
+        add_test_asset(&mut contract, "btc.near");
+        report(
+            &mut contract,
+            &accounts(1),
+            ts(10),
+            &[("eth.near", price(3000, 0)), ("btc.near", price(60000, 0))],
+        );
+        let cross = contract
+            .get_cross_price("eth.near".to_string(), "btc.near".to_string(), 4)
+            .unwrap();
+        assert_eq!((cross.multiplier, cross.decimals), (500, 4));
+    }
+
+    #[test]
+    fn test_global_min_reports() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(5, 0)));
+
+        set_context(accounts(0), ts(10));
+        contract.set_global_min_reports(3);
+        assert_eq!(spot(&contract, "wrap.near"), None);
+    }
+
+    #[test]
+    fn test_get_effective_quorum() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+        assert_eq!(contract.get_effective_quorum("wrap.near".to_string()), 1);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(5, 0)));
+
+        add_test_oracle(&mut contract, &accounts(2));
+        add_test_oracle(&mut contract, &accounts(3));
+        assert_eq!(contract.get_effective_quorum("wrap.near".to_string()), 2);
+        assert_eq!(spot(&contract, "wrap.near"), None);
+        report(&mut contract, &accounts(2), ts(20), &[("wrap.near", price(5, 0))]);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(5, 0)));
+
+        set_context(accounts(0), ts(20));
+        contract.set_global_min_reports(3);
+        assert_eq!(contract.get_effective_quorum("wrap.near".to_string()), 3);
+    }
+
+    #[test]
+    fn test_should_update_heartbeat() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        contract.set_asset_update_policy("wrap.near".to_string(), Some(600), Some(100));
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(500, 2))]);
+
+        set_context(accounts(1), ts(600));
+        assert!(!contract.should_update("wrap.near".to_string(), price(500, 2)));
+        set_context(accounts(1), ts(610));
+        assert!(contract.should_update("wrap.near".to_string(), price(500, 2)));
+    }
+
+    #[test]
+    fn test_should_update_deviation() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        assert!(contract.should_update("wrap.near".to_string(), price(500, 2)));
+        contract.set_asset_update_policy("wrap.near".to_string(), Some(600), Some(100));
+        assert!(contract.should_update("wrap.near".to_string(), price(500, 2)));
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(500, 2))]);
+
+        assert!(!contract.should_update("wrap.near".to_string(), price(504, 2)));
+        assert!(!contract.should_update("wrap.near".to_string(), price(495, 2)));
+        assert!(contract.should_update("wrap.near".to_string(), price(506, 2)));
+        assert!(contract.should_update("wrap.near".to_string(), price(494, 2)));
+    }
+
+    #[test]
+    fn test_get_price_data_normalized() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "eth.near");
+        add_test_asset(&mut contract, "usdc.near");
+        add_test_asset(&mut contract, "huge.near");
+        report(
+            &mut contract,
+            &accounts(1),
+            ts(10),
+            &[
+                ("eth.near", price(3000, 0)),
+                ("usdc.near", price(1000001, 6)),
+                ("huge.near", price(u128::MAX / 10, 0)),
+            ],
+        );
+        let prices: Vec<_> = contract
+            .get_price_data_normalized(None, 8)
+            .prices
+            .into_iter()
+            .map(|asset_price| asset_price.price)
+            .collect();
+        assert_eq!(
+            prices,
+            vec![
+                Some(price(300000000000, 8)),
+                Some(price(100000100, 8)),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_cross_price_stale_quote() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "eth.near");
+        add_test_asset(&mut contract, "btc.near");
+        report(&mut contract, &accounts(1), ts(10), &[("btc.near", price(60000, 0))]);
+        report(&mut contract, &accounts(1), ts(3700), &[("eth.near", price(3000, 0))]);
+        assert_eq!(
+            contract.get_cross_price("eth.near".to_string(), "btc.near".to_string(), 4),
+            None
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "E_TIMESTAMP: Report timestamp must be later than the last report of the oracle")]
+    fn test_report_backdated_timestamp() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(20), &[("wrap.near", price(5, 0))]);
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(6, 0))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_BATCH_TOO_LARGE: Too many prices in a report, the maximum is 2")]
+    fn test_report_batch_over_limit() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        contract.set_max_report_batch(2);
+        assert_eq!(contract.get_max_report_batch(), 2);
+        report(
+            &mut contract,
+            &accounts(1),
+            ts(10),
+            &[
+                ("wrap.near", price(5, 0)),
+                ("wrap.near", price(6, 0)),
+                ("wrap.near", price(7, 0)),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_agent_attestation() {
+        use crate::collateral::tests::{build_app_compose, build_tcb_info};
+
+        let contract = setup();
+        let app_codehash = "22".repeat(32);
+        let app_compose = build_app_compose(&[
+            ("#shade-agent-api-image", format!("api@sha256:{}", CODEHASH).as_str()),
+            ("#shade-agent-app-image", format!("app@sha256:{}", app_codehash).as_str()),
+        ]);
+        let (tcb_info, rtmr3) = build_tcb_info(&app_compose);
+        assert_eq!(
+            contract.internal_agent_attestation(b"bob.near", rtmr3.clone(), tcb_info),
+            Ok(AgentAttestation {
+                report_data: encode("bob.near"),
+                api_codehash: CODEHASH.to_string(),
+                app_codehash,
+                api_codehash_approved: true,
+                app_codehash_approved: false,
+            })
+        );
+        assert_eq!(
+            contract.internal_agent_attestation(b"bob.near", rtmr3, "{}".to_string()),
+            Err("missing event_log".to_string())
+        );
+    }
+
+    #[test]
+    fn test_verify_agent_errors() {
+        let contract = setup();
+        let collateral = serde_json::json!({
+            "tcb_info_issuer_chain": "chain",
+            "tcb_info": "{}",
+            "tcb_info_signature": "00",
+            "qe_identity_issuer_chain": "chain",
+            "qe_identity": "{}",
+            "qe_identity_signature": "00",
+        })
+        .to_string();
+        let error = |quote_hex: &str, collateral: &str| {
+            let result = contract.verify_agent(
+                quote_hex.to_string(),
+                collateral.to_string(),
+                "{}".to_string(),
+            );
+            assert!(result.attestation.is_none());
+            result.error
+        };
+        assert_eq!(error("00", "{"), Some("collateral should be valid JSON".to_string()));
+        assert_eq!(error("zz", &collateral), Some("quote_hex should be hex encoded".to_string()));
+        assert_eq!(error("00", &collateral), Some("report is not verified".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "E_EMPTY_REPORT: No prices in the report")]
+    fn test_report_empty() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        report(&mut contract, &accounts(1), ts(10), &[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_NOT_ORACLE: Not an oracle")]
+    fn test_report_not_oracle() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_CHECKSUM: Oracle bob checksum other is not approved")]
+    fn test_report_requires_approved_checksum() {
+        let mut contract = setup();
+        let mut oracle = Oracle::new();
+        oracle.codehash = Some(CODEHASH.to_string());
+        oracle.checksum = Some("other".to_string());
+        contract.internal_add_oracle(&accounts(1), oracle);
+        add_test_asset(&mut contract, "wrap.near");
+        contract.approve_checksum("expected".to_string());
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(5, 0)));
+
+        set_context(accounts(0), ts(20));
+        contract.set_require_approved_checksum(true);
+        report(&mut contract, &accounts(1), ts(30), &[("wrap.near", price(5, 0))]);
+    }
+
+    #[test]
+    fn test_report_with_approved_checksum() {
+        let mut contract = setup();
+        let mut oracle = Oracle::new();
+        oracle.codehash = Some(CODEHASH.to_string());
+        oracle.checksum = Some("expected".to_string());
+        contract.internal_add_oracle(&accounts(1), oracle);
+        add_test_asset(&mut contract, "wrap.near");
+        contract.approve_checksum("expected".to_string());
+        contract.set_require_approved_checksum(true);
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(5, 0)));
+    }
 }
@@ -11,6 +11,19 @@ pub struct Oracle {
 
     pub codehash: Option<String>,
     pub checksum: Option<String>,
+
+    pub total_near_claimed: NearToken,
+    /// Overrides the global `near_claim_amount` for this oracle.
+    pub claim_amount: Option<NearToken>,
+    /// Reports of inactive oracles are kept, but ignored when computing prices.
+    pub active: bool,
+    /// ed25519 key the oracle signs the reports relayed via `report_prices_signed` with.
+    pub public_key: Option<PublicKey>,
+    /// NEAR posted by the oracle that the owner can slash, see `deposit_bond`.
+    pub bond: NearToken,
+    /// When the latest reports of the oracle stop being fresh, by the recency durations of
+    /// their assets at the time of reporting.
+    pub fresh_until: Timestamp,
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, NearSchema)]
@@ -20,9 +33,37 @@ pub struct OracleV0 {
     pub price_reports: u64,
 }
 
+/// Stored layout of `Oracle` before the claim stats, the activity flag, the public key and
+/// the bond.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OracleV1 {
+    pub last_report: Timestamp,
+    pub price_reports: u64,
+
+    pub last_near_claim: Timestamp,
+
+    pub codehash: Option<String>,
+    pub checksum: Option<String>,
+}
+
+impl From<OracleV1> for Oracle {
+    fn from(o: OracleV1) -> Self {
+        Oracle {
+            last_report: o.last_report,
+            price_reports: o.price_reports,
+            last_near_claim: o.last_near_claim,
+            codehash: o.codehash,
+            checksum: o.checksum,
+            ..Oracle::new()
+        }
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize, NearSchema)]
 pub enum VOracle {
     V0(OracleV0),
+    V1(OracleV1),
     Current(Oracle),
 }
 
@@ -32,10 +73,9 @@ impl From<VOracle> for Oracle {
             VOracle::V0(o) => Oracle {
                 last_report: o.last_report,
                 price_reports: o.price_reports,
-                last_near_claim: 0,
-                codehash: None,
-                checksum: None,
+                ..Oracle::new()
             },
+            VOracle::V1(o) => o.into(),
             VOracle::Current(c) => c,
         }
     }
@@ -53,15 +93,20 @@ impl From<&VOracle> for Oracle {
             VOracle::V0(o) => Oracle {
                 last_report: o.last_report,
                 price_reports: o.price_reports,
-                last_near_claim: 0,
-                codehash: None,
-                checksum: None,
+                ..Oracle::new()
             },
+            VOracle::V1(o) => o.clone().into(),
             VOracle::Current(c) => c.clone(),
         }
     }
 }
 
+impl Default for Oracle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Oracle {
     pub fn new() -> Self {
         Self {
@@ -70,10 +115,88 @@ impl Oracle {
             last_near_claim: 0,
             codehash: None,
             checksum: None,
+            total_near_claimed: NearToken::from_yoctonear(0),
+            claim_amount: None,
+            active: true,
+            public_key: None,
+            bond: NearToken::from_yoctonear(0),
+            fresh_until: 0,
         }
     }
 }
 
+#[derive(Serialize, Deserialize, NearSchema)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct OracleStats {
+    pub account_id: AccountId,
+    pub last_report: Timestamp,
+    pub price_reports: u64,
+    pub last_near_claim: Timestamp,
+    pub total_near_claimed: NearToken,
+    /// Whether none of the oracle's reports are fresh anymore, by the recency durations of
+    /// their assets when they were reported.
+    pub is_stale: bool,
+    pub codehash: Option<String>,
+}
+
+#[near]
+impl Contract {
+    /// Completes a scheduled oracle removal once its deactivation timestamp has passed.
+    pub fn finalize_oracle_removal(&mut self, account_id: AccountId) {
+        let deactivate_at = *self
+            .oracle_deactivations
+            .get(&account_id)
+            .expect("Oracle removal is not scheduled");
+        assert!(
+            env::block_timestamp() >= deactivate_at,
+            "Oracle is still active"
+        );
+        self.internal_remove_oracle(&account_id);
+    }
+
+    /// Returns whether the oracle would receive its NEAR claim when reporting now, i.e. the
+    /// claim cooldown has elapsed and the contract balance covers the claim.
+    pub fn can_claim(&self, account_id: AccountId) -> bool {
+        self.internal_get_oracle(&account_id).is_some_and(|oracle| {
+            oracle.last_near_claim + to_nano(self.near_claim_cooldown_sec) <= env::block_timestamp()
+                && self.internal_can_pay_near_claim(
+                    oracle.claim_amount.unwrap_or(self.near_claim_amount),
+                )
+        })
+    }
+
+    /// Returns a page of per-oracle stats, e.g. for leaderboards and monitoring. Only reads
+    /// the oracles of the page, the stats are kept on the oracle as it reports.
+    pub fn get_oracle_stats(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<OracleStats> {
+        let timestamp = env::block_timestamp();
+        unordered_map_pagination(&self.oracles, from_index, limit)
+            .into_iter()
+            .map(|(account_id, oracle): (AccountId, Oracle)| OracleStats {
+                account_id,
+                last_report: oracle.last_report,
+                price_reports: oracle.price_reports,
+                last_near_claim: oracle.last_near_claim,
+                total_near_claimed: oracle.total_near_claimed,
+                is_stale: oracle.fresh_until <= timestamp,
+                codehash: oracle.codehash,
+            })
+            .collect()
+    }
+
+    /// Returns the number of oracles running each codehash, sorted by codehash. Oracles
+    /// without a codehash are not counted.
+    pub fn get_codehash_distribution(&self) -> Vec<(String, u32)> {
+        let mut distribution = std::collections::BTreeMap::new();
+        for v_oracle in self.oracles.values() {
+            if let Some(codehash) = Oracle::from(v_oracle).codehash {
+                *distribution.entry(codehash).or_insert(0) += 1;
+            }
+        }
+        distribution.into_iter().collect()
+    }
+}
+
 impl Contract {
     pub fn internal_get_oracle(&self, account_id: &AccountId) -> Option<Oracle> {
         self.oracles.get(account_id).map(|o| o.into())
@@ -82,4 +205,181 @@ impl Contract {
     pub fn internal_set_oracle(&mut self, account_id: &AccountId, oracle: Oracle) {
         self.oracles.insert(account_id.clone(), oracle.into());
     }
+
+    /// Returns the index interning the oracle ID in the stored reports, assigning the next one
+    /// for a new ID. Indices are never reused, since the reports of a removed oracle may
+    /// remain until `clean_oracle_data`.
+    pub(crate) fn internal_oracle_index(&mut self, account_id: &AccountId) -> u32 {
+        if let Some(index) = self.oracle_indices.get(account_id) {
+            return *index;
+        }
+        let index = self.oracle_ids_by_index.len();
+        self.oracle_ids_by_index.push(account_id.clone());
+        self.oracle_indices.insert(account_id.clone(), index);
+        index
+    }
+
+    /// Registers a new oracle, enforcing uniqueness and the `max_oracles` cap.
+    pub fn internal_add_oracle(&mut self, account_id: &AccountId, oracle: Oracle) {
+        assert!(
+            self.internal_get_oracle(account_id).is_none(),
+            "Oracle already exists"
+        );
+        if let Some(max_oracles) = self.max_oracles {
+            assert!(
+                self.oracles.len() < max_oracles,
+                "Maximum number of oracles reached"
+            );
+        }
+        self.internal_oracle_index(account_id);
+        self.internal_set_oracle(account_id, oracle);
+    }
+
+    /// Removes the oracle with its scheduled removal. Its remaining bond stays locked until
+    /// `withdraw_bond`, so it can still be slashed during the cooldown. The reports of the
+    /// oracle are kept.
+    pub(crate) fn internal_remove_oracle(&mut self, account_id: &AccountId) -> Option<Oracle> {
+        self.oracle_deactivations.remove(account_id);
+        let oracle: Oracle = self.oracles.remove(account_id)?.into();
+        if !oracle.bond.is_zero() {
+            let removed_bond = self
+                .removed_oracle_bonds
+                .entry(account_id.clone())
+                .or_insert(RemovedOracleBond {
+                    bond: NearToken::from_yoctonear(0),
+                    last_report: 0,
+                });
+            removed_bond.bond = removed_bond.bond.saturating_add(oracle.bond);
+            removed_bond.last_report = std::cmp::max(removed_bond.last_report, oracle.last_report);
+        }
+        Some(oracle)
+    }
+
+    /// Balance available for NEAR claims, excluding the balance locked for storage and the
+    /// oracle bonds.
+    pub(crate) fn internal_liquid_balance(&self) -> u128 {
+        (env::account_balance().as_yoctonear() + env::account_locked_balance().as_yoctonear())
+            .saturating_sub(
+                env::storage_byte_cost().as_yoctonear() * u128::from(env::storage_usage()),
+            )
+            .saturating_sub(self.total_bonded.as_yoctonear())
+    }
+
+    pub(crate) fn internal_can_pay_near_claim(&self, near_claim_amount: NearToken) -> bool {
+        self.internal_liquid_balance()
+            > near_claim_amount.as_yoctonear() + self.claim_safety_margin.as_yoctonear()
+    }
+
+    /// Returns the oracles whose reports are excluded from the prices.
+    pub(crate) fn internal_inactive_oracle_ids(&self) -> HashSet<AccountId> {
+        self.oracles
+            .iter()
+            .filter(|(_, v)| {
+                let oracle: Oracle = (*v).into();
+                !oracle.active
+            })
+            .map(|(account_id, _)| account_id.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::*;
+    use crate::*;
+    use near_sdk::test_utils::accounts;
+
+    #[test]
+    fn test_get_oracle_stats() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_oracle(&mut contract, &accounts(2));
+        add_test_asset(&mut contract, "wrap.near");
+        add_test_asset(&mut contract, "dai.near");
+        report(
+            &mut contract,
+            &accounts(1),
+            ts(10),
+            &[("wrap.near", price(1, 0)), ("dai.near", price(1, 0))],
+        );
+
+        set_context(accounts(0), ts(20));
+        let stats = contract.get_oracle_stats(None, None);
+        assert_eq!(stats.len(), 2);
+        for stat in stats {
+            let oracle = contract.get_oracle(stat.account_id.clone()).unwrap();
+            assert_eq!(stat.last_report, oracle.last_report);
+            assert_eq!(stat.price_reports, oracle.price_reports);
+            assert_eq!(stat.last_near_claim, oracle.last_near_claim);
+            assert_eq!(stat.total_near_claimed, oracle.total_near_claimed);
+            assert_eq!(stat.codehash, oracle.codehash);
+            assert_eq!(stat.is_stale, stat.account_id == accounts(2));
+        }
+        assert_eq!(contract.get_oracle_stats(Some(1), Some(5)).len(), 1);
+
+        set_context(accounts(0), ts(3610));
+        assert!(contract.get_oracle_stats(None, None).iter().all(|stat| stat.is_stale));
+    }
+
+    #[test]
+    fn test_get_codehash_distribution() {
+        let mut contract = setup();
+        let other_codehash = "ab".repeat(32);
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_oracle(&mut contract, &accounts(2));
+        let mut oracle = Oracle::new();
+        oracle.codehash = Some(other_codehash.clone());
+        contract.internal_add_oracle(&accounts(3), oracle);
+        contract.internal_add_oracle(&accounts(4), Oracle::new());
+
+        assert_eq!(
+            contract.get_codehash_distribution(),
+            vec![(CODEHASH.to_string(), 2), (other_codehash, 1)]
+        );
+    }
+
+    #[test]
+    fn test_oracle_stats_count_reports() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        add_test_asset(&mut contract, "dai.near");
+        let stats = |contract: &Contract| {
+            let stat = contract.get_oracle_stats(None, None).remove(0);
+            (stat.price_reports, stat.last_report)
+        };
+        assert_eq!(stats(&contract), (0, 0));
+
+        report(
+            &mut contract,
+            &accounts(1),
+            ts(10),
+            &[("wrap.near", price(1, 0)), ("dai.near", price(1, 0))],
+        );
+        assert_eq!(stats(&contract), (2, ts(10)));
+
+        report(&mut contract, &accounts(1), ts(20), &[("wrap.near", price(2, 0))]);
+        assert_eq!(stats(&contract), (3, ts(20)));
+    }
+
+    #[test]
+    fn test_oracle_v1() {
+        let mut contract = setup();
+        contract.oracles.insert(
+            accounts(1),
+            VOracle::V1(OracleV1 {
+                last_report: ts(10),
+                price_reports: 3,
+                last_near_claim: ts(5),
+                codehash: Some(CODEHASH.to_string()),
+                checksum: None,
+            }),
+        );
+        let oracle = contract.get_oracle(accounts(1)).unwrap();
+        assert_eq!((oracle.last_report, oracle.price_reports), (ts(10), 3));
+        assert_eq!(oracle.last_near_claim, ts(5));
+        assert_eq!(oracle.codehash.as_deref(), Some(CODEHASH));
+        assert!(oracle.active);
+        assert_eq!(oracle.bond, NearToken::from_yoctonear(0));
+    }
 }
@@ -18,45 +18,264 @@ impl Contract {
     //     self.internal_set_oracle(&account_id, Oracle::new());
     // }
 
-    //#[payable]
-    pub fn remove_oracle(&mut self, account_id: AccountId) {
-        //assert_one_yocto();
+    /// Registers an explicitly trusted oracle without the TEE attestation of `register_agent`,
+    /// e.g. for permissioned deployments. The codehash must be approved, and reports keep
+    /// requiring it to stay approved. Requires a deposit covering the oracle storage, the
+    /// excess is refunded.
+    #[payable]
+    pub fn add_trusted_oracle(&mut self, account_id: AccountId, codehash: String) {
         self.assert_owner();
-        assert!(self.oracles.remove(&account_id).is_some());
+        let initial_storage_usage = env::storage_usage();
+        let codehash = normalize_codehash(&codehash);
+        assert!(
+            self.internal_is_codehash_approved(&codehash),
+            "Codehash is not approved"
+        );
+        let mut oracle = Oracle::new();
+        oracle.codehash = Some(codehash);
+        self.internal_add_oracle(&account_id, oracle);
+        self.internal_log_owner_action("add_trusted_oracle", &account_id);
+        self.oracles.flush();
+        self.owner_actions.flush();
+        self.internal_refund_storage_deposit(initial_storage_usage);
     }
 
-    //#[payable]
+    /// Removes the oracle and returns it, if it was registered. Its remaining bond can be
+    /// withdrawn after the cooldown, and its reports are kept until `clean_oracle_data` is
+    /// called.
+    #[payable]
+    pub fn remove_oracle(&mut self, account_id: AccountId) -> Option<Oracle> {
+        assert_one_yocto();
+        self.assert_admin();
+        let oracle = self.internal_remove_oracle(&account_id)?;
+        self.internal_log_owner_action("remove_oracle", &account_id);
+        log!(
+            "Oracle {} is removed, its reports remain until clean_oracle_data is called",
+            account_id
+        );
+        Some(oracle)
+    }
+
+    /// Schedules the oracle removal. The oracle can't report anymore, but it keeps counting
+    /// toward the quorum until `deactivate_at`, so the quorum doesn't drop abruptly. After that
+    /// anyone can finalize the removal with `finalize_oracle_removal`.
+    #[payable]
+    pub fn remove_oracle_at(&mut self, account_id: AccountId, deactivate_at: Timestamp) {
+        assert_one_yocto();
+        self.assert_admin();
+        assert!(
+            deactivate_at > env::block_timestamp(),
+            "Deactivation must be in the future"
+        );
+        assert!(self.oracles.contains_key(&account_id), "Unknown oracle");
+        self.internal_log_owner_action("remove_oracle_at", &account_id);
+        self.oracle_deactivations.insert(account_id, deactivate_at);
+    }
+
+    /// Removes the oracle and purges its reports from the given assets, or from all assets if
+    /// `asset_ids` is not provided. Unscoped calls iterate every asset and may exceed the gas
+    /// limit on large deployments.
+    #[payable]
+    pub fn revoke_oracle(&mut self, account_id: AccountId, asset_ids: Option<Vec<AssetId>>) {
+        assert_one_yocto();
+        self.assert_admin();
+        assert!(self.internal_remove_oracle(&account_id).is_some(), "Unknown oracle");
+        self.internal_log_owner_action("revoke_oracle", &account_id);
+        let asset_ids = asset_ids.unwrap_or_else(|| self.assets.keys().cloned().collect());
+        for asset_id in asset_ids {
+            if let Some(mut asset) = self.internal_get_asset(&asset_id) {
+                if asset.remove_report(&account_id) {
+                    self.internal_set_asset(&asset_id, asset);
+                }
+            }
+        }
+    }
+
+    /// Requires a deposit covering the asset storage, the excess is refunded.
+    #[payable]
     pub fn add_asset(&mut self, asset_id: AssetId) {
-        self.assert_owner();
+        self.assert_admin();
+        let initial_storage_usage = env::storage_usage();
         assert!(self.internal_get_asset(&asset_id).is_none());
-        self.internal_set_asset(&asset_id, Asset::new());
+        assert!(
+            !self.asset_aliases.contains_key(&asset_id),
+            "Asset ID is already an alias"
+        );
+        let asset = Asset {
+            created_at: env::block_timestamp(),
+            ..Asset::new()
+        };
+        self.internal_set_asset(&asset_id, asset);
+        self.internal_log_owner_action("add_asset", &asset_id);
+        self.assets.flush();
+        self.owner_actions.flush();
+        self.internal_refund_storage_deposit(initial_storage_usage);
     }
 
+    /// Removes the asset with its history, the aliases resolving to it, and the fallbacks from
+    /// and to it.
     //#[payable]
     pub fn remove_asset(&mut self, asset_id: AssetId) {
         //assert_one_yocto();
-        self.assert_owner();
+        self.assert_admin();
         assert!(self.assets.remove(&asset_id).is_some());
+        self.asset_histories.remove(&asset_id);
+        let alias_ids: Vec<AssetId> = self
+            .asset_aliases
+            .keys()
+            .filter(|alias_id| self.internal_resolve_asset_id(alias_id) == asset_id)
+            .cloned()
+            .collect();
+        let fallback_ids: Vec<AssetId> = self
+            .asset_fallbacks
+            .iter()
+            .filter(|(from_id, to_id)| {
+                **from_id == asset_id || self.internal_resolve_asset_id(to_id) == asset_id
+            })
+            .map(|(from_id, _)| from_id.clone())
+            .collect();
+        for alias_id in alias_ids {
+            self.asset_aliases.remove(&alias_id);
+        }
+        for fallback_id in fallback_ids {
+            self.asset_fallbacks.remove(&fallback_id);
+        }
+        self.internal_log_owner_action("remove_asset", &asset_id);
     }
 
+    /// Moves the asset with its reports, EMAs, history, fallback, and the aliases and fallbacks
+    /// pointing at it to `new_id`. With `keep_alias`, the old ID keeps serving the new ID's
+    /// prices until the alias is removed. If `new_id` is an alias, it's only replaced with
+    /// `replace_alias`.
+    pub fn rename_asset(
+        &mut self,
+        old_id: AssetId,
+        new_id: AssetId,
+        keep_alias: Option<bool>,
+        replace_alias: Option<bool>,
+    ) {
+        self.assert_admin();
+        assert!(self.internal_get_asset(&new_id).is_none(), "Asset already exists");
+        assert!(
+            replace_alias.unwrap_or(false) || !self.asset_aliases.contains_key(&new_id),
+            "Asset ID is already an alias"
+        );
+        let asset = self.assets.remove(&old_id).expect("Missing an asset");
+        self.assets.insert(new_id.clone(), asset);
+        if let Some(history) = self.asset_histories.remove(&old_id) {
+            self.asset_histories.insert(new_id.clone(), history);
+        }
+        self.asset_aliases.remove(&new_id);
+        self.internal_retarget_asset_id(&old_id, &new_id);
+        if let Some(fallback_id) = self.asset_fallbacks.remove(&old_id) {
+            self.asset_fallbacks.insert(new_id.clone(), fallback_id);
+        }
+        self.internal_log_owner_action("rename_asset", format!("{} -> {}", old_id, new_id));
+        if keep_alias.unwrap_or(false) {
+            self.asset_aliases.insert(old_id, new_id);
+        }
+    }
+
+    /// Makes `old_id` serve the prices of `new_id`, e.g. after a token contract redeploys.
+    /// The target can itself be an alias, as long as it doesn't lead back to `old_id`.
+    pub fn set_asset_alias(&mut self, old_id: AssetId, new_id: AssetId) {
+        self.assert_admin();
+        assert!(!self.assets.contains_key(&old_id), "Asset already exists");
+        let mut target_id = new_id.clone();
+        loop {
+            assert_ne!(target_id, old_id, "Asset alias would create a cycle");
+            match self.asset_aliases.get(&target_id) {
+                Some(next_id) => target_id = next_id.clone(),
+                None => break,
+            }
+        }
+        assert!(self.assets.contains_key(&target_id), "Missing an asset");
+        self.internal_log_owner_action("set_asset_alias", format!("{} -> {}", old_id, new_id));
+        self.asset_aliases.insert(old_id, new_id);
+    }
+
+    pub fn remove_asset_alias(&mut self, asset_id: AssetId) {
+        self.assert_admin();
+        assert!(self.asset_aliases.remove(&asset_id).is_some(), "Missing an alias");
+        self.internal_log_owner_action("remove_asset_alias", &asset_id);
+    }
+
+    /// Makes `get_price_data` serve the price of `fallback_id` when `asset_id` has no fresh
+    /// price. Fallbacks are followed up to `MAX_FALLBACK_DEPTH` times. `None` removes it.
+    pub fn set_asset_fallback(&mut self, asset_id: AssetId, fallback_id: Option<AssetId>) {
+        self.assert_admin();
+        match fallback_id {
+            Some(fallback_id) => {
+                assert_ne!(asset_id, fallback_id, "Asset can't be its own fallback");
+                assert!(
+                    self.internal_get_asset(&self.internal_resolve_asset_id(&fallback_id))
+                        .is_some(),
+                    "Missing a fallback asset"
+                );
+                self.asset_fallbacks.insert(asset_id, fallback_id);
+            }
+            None => {
+                assert!(self.asset_fallbacks.remove(&asset_id).is_some(), "Missing a fallback");
+            }
+        }
+    }
+
+    pub fn get_asset_fallback(&self, asset_id: AssetId) -> Option<AssetId> {
+        self.asset_fallbacks.get(&asset_id).cloned()
+    }
+
+    /// Adds an EMA with the given period to the asset. `alpha_bps` pins a fixed smoothing
+    /// factor instead of deriving it from the period. With `seed_from_spot`, the EMA starts
+    /// at the current fresh median. Without it, or if there is no fresh median, the EMA starts
+    /// at the first median computed after a report.
+    ///
+    /// An EMA is served while it was updated within the recency duration of the asset,
+    /// regardless of its period. A period shorter than the recency duration is allowed, but
+    /// logs a warning, since such an EMA smooths over less time than a spot report stays fresh.
     //#[payable]
-    pub fn add_asset_ema(&mut self, asset_id: AssetId, period_sec: DurationSec) {
+    pub fn add_asset_ema(
+        &mut self,
+        asset_id: AssetId,
+        period_sec: DurationSec,
+        alpha_bps: Option<u16>,
+        seed_from_spot: Option<bool>,
+    ) {
         //assert_one_yocto();
-        self.assert_owner();
+        self.assert_admin();
         let mut asset = self
             .internal_get_asset(&asset_id)
             .expect("Missing an asset");
         if asset.emas.iter().any(|ema| ema.period_sec == period_sec) {
             panic!("EMA for this period already exists");
         }
-        asset.emas.push(AssetEma::new(period_sec));
+        let mut ema = AssetEma::new(period_sec, alpha_bps);
+        let recency_duration_sec = self.internal_recency_duration_sec(&asset_id);
+        if period_sec < recency_duration_sec {
+            log!(
+                "Warning! EMA period {} of {} is shorter than its recency duration of {} seconds",
+                period_sec,
+                asset_id,
+                recency_duration_sec
+            );
+        }
+        if seed_from_spot.unwrap_or(false) {
+            let timestamp = env::block_timestamp();
+            if let Some(median_price) = asset.active_median_price(
+                self.internal_timestamp_cut(&asset_id, timestamp),
+                asset.min_num_recent_reports(self.internal_min_num_recent_reports()),
+                &self.internal_inactive_oracle_ids(),
+            ) {
+                ema.recompute(median_price, timestamp);
+            }
+        }
+        asset.emas.push(ema);
         self.internal_set_asset(&asset_id, asset);
     }
 
     //#[payable]
     pub fn remove_asset_ema(&mut self, asset_id: AssetId, period_sec: DurationSec) {
         //assert_one_yocto();
-        self.assert_owner();
+        self.assert_admin();
         let mut asset = self
             .internal_get_asset(&asset_id)
             .expect("Missing an asset");
@@ -69,6 +288,336 @@ impl Contract {
         self.internal_set_asset(&asset_id, asset);
     }
 
+    /// Bootstraps the EMA with a value computed off-chain, so a newly added EMA is served right
+    /// away. Only allowed while the EMA has no value within the recency duration of the asset.
+    pub fn set_asset_ema_value(
+        &mut self,
+        asset_id: AssetId,
+        period_sec: DurationSec,
+        price: Price,
+        timestamp: Timestamp,
+    ) {
+        self.assert_owner();
+        let block_timestamp = env::block_timestamp();
+        assert!(timestamp <= block_timestamp, "EMA timestamp is in the future");
+        let timestamp_cut = self.internal_timestamp_cut(&asset_id, block_timestamp);
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        asset.assert_valid_price(&price);
+        let ema = asset
+            .emas
+            .iter_mut()
+            .find(|ema| ema.period_sec == period_sec)
+            .expect("EMA for this period doesn't exists");
+        assert!(
+            ema.price.is_none() || ema.timestamp < timestamp_cut,
+            "EMA already has recent data"
+        );
+        ema.price = Some(price);
+        ema.timestamp = timestamp;
+        self.internal_set_asset(&asset_id, asset);
+    }
+
+    /// Clears the values of the asset's EMAs, keeping their periods and smoothing factors, so
+    /// they re-seed from the next median, e.g. after a fix of the EMA math. This discards the
+    /// EMA history, and the EMAs aren't served until the next report or `touch_emas`.
+    pub fn reset_asset_emas(&mut self, asset_id: AssetId) {
+        self.assert_owner();
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        asset.emas.iter_mut().for_each(AssetEma::reset);
+        self.internal_set_asset(&asset_id, asset);
+    }
+
+    /// Same as `reset_asset_emas` for a page of all assets. Returns the number of assets whose
+    /// EMAs were reset.
+    pub fn reset_all_emas(&mut self, from_index: Option<u64>, limit: Option<u64>) -> u32 {
+        self.assert_owner();
+        let asset_ids: Vec<AssetId> = self
+            .assets
+            .keys()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.map_or(usize::MAX, |limit| limit as usize))
+            .cloned()
+            .collect();
+        let mut num_reset = 0;
+        for asset_id in asset_ids {
+            let mut asset = self.internal_get_asset(&asset_id).unwrap();
+            if asset.emas.is_empty() {
+                continue;
+            }
+            asset.emas.iter_mut().for_each(AssetEma::reset);
+            self.internal_set_asset(&asset_id, asset);
+            num_reset += 1;
+        }
+        num_reset
+    }
+
+    /// Sets the maximum number of oracles. `None` removes the cap. Lowering the cap below the
+    /// current number of oracles doesn't remove any, but blocks new registrations.
+    pub fn set_max_oracles(&mut self, max_oracles: Option<u32>) {
+        self.assert_owner();
+        self.max_oracles = max_oracles;
+    }
+
+    pub fn get_max_oracles(&self) -> Option<u32> {
+        self.max_oracles
+    }
+
+    /// Sets the minimum time between two reports of the same oracle. 0 disables the limit.
+    pub fn set_min_report_interval_sec(&mut self, min_report_interval_sec: DurationSec) {
+        self.assert_owner();
+        self.min_report_interval_sec = min_report_interval_sec;
+    }
+
+    pub fn get_min_report_interval_sec(&self) -> DurationSec {
+        self.min_report_interval_sec
+    }
+
+    /// Enables pruning of the reports older than the threshold whenever `report_prices` updates
+    /// an asset. `None` disables pruning, so stale reports stay until removed explicitly.
+    pub fn set_report_prune_threshold_sec(
+        &mut self,
+        report_prune_threshold_sec: Option<DurationSec>,
+    ) {
+        self.assert_owner();
+        self.report_prune_threshold_sec = report_prune_threshold_sec;
+    }
+
+    pub fn get_report_prune_threshold_sec(&self) -> Option<DurationSec> {
+        self.report_prune_threshold_sec
+    }
+
+    /// Drops the reports of the asset older than `older_than_sec`. Returns the number of
+    /// dropped reports.
+    pub fn prune_asset(&mut self, asset_id: AssetId, older_than_sec: DurationSec) -> u32 {
+        self.assert_admin();
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        let num_pruned = asset
+            .prune_reports(env::block_timestamp().saturating_sub(to_nano(older_than_sec)));
+        if num_pruned > 0 {
+            self.internal_set_asset(&asset_id, asset);
+        }
+        num_pruned
+    }
+
+    /// Sets the daily market sessions of the asset. Prices whose latest report falls outside
+    /// of every session are still served, but flagged as `OffSession`.
+    pub fn set_asset_sessions(&mut self, asset_id: AssetId, sessions: Vec<SessionWindow>) {
+        self.assert_admin();
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        sessions.iter().for_each(|session| session.assert_valid());
+        asset.sessions = sessions;
+        self.internal_set_asset(&asset_id, asset);
+    }
+
+    /// Sets the outlier tolerance of the asset in basis points from the provisional median.
+    /// `None` disables outlier filtering.
+    pub fn set_asset_outlier_bps(&mut self, asset_id: AssetId, bps: Option<u32>) {
+        self.assert_admin();
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        asset.outlier_bps = bps;
+        self.internal_set_asset(&asset_id, asset);
+    }
+
+    /// Sets the interquartile range multiplier of the asset's outlier filter in basis points,
+    /// e.g. 15000 for the usual 1.5. `None` disables the filter.
+    pub fn set_asset_outlier_iqr(&mut self, asset_id: AssetId, k_bps: Option<u32>) {
+        self.assert_admin();
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        asset.outlier_iqr_k_bps = k_bps;
+        self.internal_set_asset(&asset_id, asset);
+    }
+
+    /// Sets how the fresh reports of the asset are aggregated into its spot price. EMAs and
+    /// history snapshots keep following the median.
+    pub fn set_asset_aggregation(&mut self, asset_id: AssetId, aggregation: AggregationMethod) {
+        self.assert_admin();
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        asset.aggregation = aggregation;
+        self.internal_set_asset(&asset_id, asset);
+    }
+
+    /// Disables serving the spot median for the bare asset ID, forcing consumers onto EMAs.
+    pub fn set_asset_spot_serving_disabled(&mut self, asset_id: AssetId, disabled: bool) {
+        self.assert_admin();
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        asset.spot_serving_disabled = disabled;
+        self.internal_set_asset(&asset_id, asset);
+    }
+
+    pub fn set_asset_metadata(&mut self, asset_id: AssetId, metadata: Option<AssetMetadata>) {
+        self.assert_admin();
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        if let Some(token_decimals) = metadata.as_ref().and_then(|m| m.token_decimals) {
+            assert!(
+                token_decimals <= MAX_VALID_DECIMALS,
+                "Token decimals can't exceed {}",
+                MAX_VALID_DECIMALS
+            );
+        }
+        asset.metadata = metadata;
+        self.internal_set_asset(&asset_id, asset);
+    }
+
+    /// Allows reports with decimals above 38 for the asset. Prices with such decimals can't
+    /// always be compared exactly, so it's disabled by default.
+    pub fn set_asset_allow_high_decimals(&mut self, asset_id: AssetId, allow_high_decimals: bool) {
+        self.assert_admin();
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        asset.allow_high_decimals = allow_high_decimals;
+        self.internal_set_asset(&asset_id, asset);
+    }
+
+    /// Overrides the recency duration for the asset. `None` falls back to the contract's one.
+    pub fn set_asset_recency_duration_sec(
+        &mut self,
+        asset_id: AssetId,
+        recency_duration_sec: Option<DurationSec>,
+    ) {
+        self.assert_admin();
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        asset.recency_duration_sec = recency_duration_sec;
+        self.internal_set_asset(&asset_id, asset);
+    }
+
+    /// Temporarily stops serving prices of the asset without dropping its reports and EMAs.
+    pub fn set_asset_disabled(&mut self, asset_id: AssetId, disabled: bool) {
+        self.assert_admin();
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        asset.disabled = disabled;
+        self.internal_set_asset(&asset_id, asset);
+    }
+
+    /// Suppresses the spot price of the asset when it deviates from its EMA with the given
+    /// period by more than `max_deviation_bps`.
+    pub fn set_asset_ema_guard(
+        &mut self,
+        asset_id: AssetId,
+        period_sec: DurationSec,
+        max_deviation_bps: u32,
+    ) {
+        self.assert_admin();
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        assert!(
+            asset.emas.iter().any(|ema| ema.period_sec == period_sec),
+            "Missing an EMA for this period"
+        );
+        asset.ema_guard = Some(EmaGuard {
+            period_sec,
+            max_deviation_bps,
+        });
+        self.internal_set_asset(&asset_id, asset);
+    }
+
+    pub fn remove_asset_ema_guard(&mut self, asset_id: AssetId) {
+        self.assert_admin();
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        asset.ema_guard = None;
+        self.internal_set_asset(&asset_id, asset);
+    }
+
+    /// Allows negative reports for the asset, e.g. funding rates or basis spreads.
+    pub fn set_asset_signed(&mut self, asset_id: AssetId, signed: bool) {
+        self.assert_admin();
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        asset.signed = signed;
+        self.internal_set_asset(&asset_id, asset);
+    }
+
+    /// Sets when oracles should report the asset, see `should_update`. `None` disables the
+    /// corresponding trigger.
+    pub fn set_asset_update_policy(
+        &mut self,
+        asset_id: AssetId,
+        heartbeat_sec: Option<DurationSec>,
+        deviation_threshold_bps: Option<u32>,
+    ) {
+        self.assert_admin();
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        asset.heartbeat_sec = heartbeat_sec;
+        asset.deviation_threshold_bps = deviation_threshold_bps;
+        self.internal_set_asset(&asset_id, asset);
+    }
+
+    /// Allows zero reports for the asset. They're rejected by default.
+    pub fn set_asset_allow_zero_price(&mut self, asset_id: AssetId, allow_zero_price: bool) {
+        self.assert_admin();
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        asset.allow_zero_price = allow_zero_price;
+        self.internal_set_asset(&asset_id, asset);
+    }
+
+    /// Sets the sanity bounds of the asset reports. `None` removes the bound.
+    pub fn set_asset_price_bounds(
+        &mut self,
+        asset_id: AssetId,
+        min_price: Option<Price>,
+        max_price: Option<Price>,
+    ) {
+        self.assert_admin();
+        if let Some((min_price, max_price)) = min_price.zip(max_price) {
+            assert!(min_price <= max_price, "The minimum price exceeds the maximum price");
+        }
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        asset.min_price = min_price;
+        asset.max_price = max_price;
+        self.internal_set_asset(&asset_id, asset);
+    }
+
+    /// Sets the report validation policy of the asset. `flags` is a bitmask of the `VALIDATE_*`
+    /// checks to enable, configured by `params`.
+    pub fn set_asset_validation(
+        &mut self,
+        asset_id: AssetId,
+        flags: u32,
+        params: Option<AssetValidationParams>,
+    ) {
+        self.assert_admin();
+        let mut asset = self
+            .internal_get_asset(&asset_id)
+            .expect("Missing an asset");
+        asset.validation = AssetValidation {
+            flags,
+            params: params.unwrap_or_default(),
+        };
+        self.internal_set_asset(&asset_id, asset);
+    }
+
     pub fn get_owner_id(&self) -> AccountId {
         self.owner_id.clone()
     }
@@ -84,18 +633,236 @@ impl Contract {
         self.near_claim_amount = NearToken::from_yoctonear(near_claim_amount.into());
     }
 
-    //#[payable]
-    pub fn update_owner_id(&mut self, owner_id: AccountId) {
-        //assert_one_yocto();
+    /// Sets the minimum time between two NEAR claims of the same oracle.
+    pub fn set_near_claim_cooldown(&mut self, near_claim_cooldown_sec: DurationSec) {
         self.assert_owner();
-        self.owner_id = owner_id;
+        self.near_claim_cooldown_sec = near_claim_cooldown_sec;
+    }
+
+    pub fn get_near_claim_cooldown(&self) -> DurationSec {
+        self.near_claim_cooldown_sec
+    }
+
+    /// Sets the balance that must remain on top of a NEAR claim for it to be paid.
+    pub fn set_claim_safety_margin(&mut self, claim_safety_margin: U128) {
+        self.assert_owner();
+        self.claim_safety_margin = NearToken::from_yoctonear(claim_safety_margin.0);
+    }
+
+    pub fn get_claim_safety_margin(&self) -> U128 {
+        U128(self.claim_safety_margin.as_yoctonear())
+    }
+
+    /// Sets the minimum number of fresh reports required to serve a price, on top of the
+    /// majority of the oracles. The per-asset `min_reporters` can only raise it further.
+    pub fn set_global_min_reports(&mut self, global_min_reports: u32) {
+        self.assert_owner();
+        self.global_min_reports = global_min_reports;
+    }
+
+    pub fn get_global_min_reports(&self) -> u32 {
+        self.global_min_reports
+    }
+
+    /// Sets the maximum number of prices an oracle can report in a single call.
+    pub fn set_max_report_batch(&mut self, max_report_batch: u32) {
+        self.assert_owner();
+        assert!(max_report_batch > 0, "The maximum report batch must be positive");
+        self.max_report_batch = max_report_batch;
+    }
+
+    pub fn get_max_report_batch(&self) -> u32 {
+        self.max_report_batch
+    }
+
+    /// Sets the maximum number of assets the price data views return without explicit asset IDs.
+    pub fn set_max_price_data_assets(&mut self, max_price_data_assets: u32) {
+        self.assert_owner();
+        assert!(
+            max_price_data_assets > 0,
+            "The maximum number of price data assets must be positive"
+        );
+        self.max_price_data_assets = max_price_data_assets;
+    }
+
+    pub fn get_max_price_data_assets(&self) -> u32 {
+        self.max_price_data_assets
+    }
+
+    /// Sets the maximum length in bytes of the `msg` forwarded by `oracle_call`.
+    pub fn set_max_oracle_call_msg_len(&mut self, max_oracle_call_msg_len: u32) {
+        self.assert_owner();
+        self.max_oracle_call_msg_len = max_oracle_call_msg_len;
+    }
+
+    pub fn get_max_oracle_call_msg_len(&self) -> u32 {
+        self.max_oracle_call_msg_len
+    }
+
+    /// Overrides the amount of NEAR the oracle receives per claim. `None` resets it to the
+    /// global `near_claim_amount`.
+    pub fn set_oracle_claim_amount(&mut self, account_id: AccountId, amount: Option<U128>) {
+        self.assert_owner();
+        let mut oracle = self
+            .internal_get_oracle(&account_id)
+            .expect("Not an oracle");
+        oracle.claim_amount = amount.map(|amount| NearToken::from_yoctonear(amount.0));
+        self.internal_set_oracle(&account_id, oracle);
+    }
+
+    /// Zeroes the report counter of the oracle, e.g. after it is re-onboarded. With
+    /// `reset_timestamps`, also clears its last report and last NEAR claim, which lifts the
+    /// report interval and the claim cooldown until its next report.
+    #[payable]
+    pub fn reset_oracle_stats(&mut self, account_id: AccountId, reset_timestamps: Option<bool>) {
+        assert_one_yocto();
+        self.assert_owner();
+        let mut oracle = self
+            .internal_get_oracle(&account_id)
+            .expect("Not an oracle");
+        oracle.price_reports = 0;
+        if reset_timestamps.unwrap_or(false) {
+            oracle.last_report = 0;
+            oracle.last_near_claim = 0;
+            oracle.fresh_until = 0;
+        }
+        self.internal_set_oracle(&account_id, oracle);
+        self.internal_log_owner_action("reset_oracle_stats", &account_id);
+    }
+
+    /// Quarantines or restores the oracle. The reports of an inactive oracle are kept, but
+    /// ignored when computing prices.
+    pub fn set_oracle_active(&mut self, account_id: AccountId, active: bool) {
+        self.assert_admin();
+        let mut oracle = self
+            .internal_get_oracle(&account_id)
+            .expect("Not an oracle");
+        oracle.active = active;
+        self.internal_set_oracle(&account_id, oracle);
+        self.internal_log_owner_action("set_oracle_active", &account_id);
+    }
+
+    /// Proposes a new owner. The ownership is transferred once the proposed account calls
+    /// `accept_ownership`, so a typo can't lock the owner out.
+    #[payable]
+    pub fn propose_new_owner(&mut self, account_id: AccountId) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.internal_log_owner_action("propose_new_owner", &account_id);
+        self.pending_owner = Some(account_id);
+    }
+
+    pub fn accept_ownership(&mut self) {
+        let pending_owner = self.pending_owner.take().expect("No pending owner");
+        assert_eq!(
+            pending_owner,
+            env::predecessor_account_id(),
+            "Can only be called by the pending owner"
+        );
+        self.internal_log_owner_action("accept_ownership", &pending_owner);
+        self.owner_id = pending_owner;
+    }
+
+    pub fn get_pending_owner(&self) -> Option<AccountId> {
+        self.pending_owner.clone()
+    }
+
+    /// Allows the account to call operational methods, e.g. managing assets and codehashes.
+    pub fn add_admin(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        self.internal_log_owner_action("add_admin", &account_id);
+        assert!(self.admins.insert(account_id), "Already an admin");
+    }
+
+    pub fn remove_admin(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        assert!(self.admins.remove(&account_id), "Not an admin");
+        self.internal_log_owner_action("remove_admin", &account_id);
+    }
+
+    pub fn get_admins(&self) -> Vec<AccountId> {
+        self.admins.iter().cloned().collect()
     }
 
     //#[payable]
     pub fn approve_codehash(&mut self, codehash: String) {
         //assert_one_yocto();
+        self.assert_admin();
+        let codehash = normalize_codehash(&codehash);
+        self.internal_log_owner_action("approve_codehash", &codehash);
+        self.approved_codehashes
+            .insert(codehash, CodehashApproval::new(None));
+    }
+
+    /// Approves a codehash until the given timestamp (in nanoseconds). After `expires_at`
+    /// oracles running this codehash can no longer report prices.
+    pub fn approve_codehash_until(&mut self, codehash: String, expires_at: Timestamp) {
+        self.assert_admin();
+        assert!(
+            expires_at > env::block_timestamp(),
+            "Expiration must be in the future"
+        );
+        let codehash = normalize_codehash(&codehash);
+        self.internal_log_owner_action("approve_codehash_until", &codehash);
+        self.approved_codehashes
+            .insert(codehash, CodehashApproval::new(Some(expires_at)));
+    }
+
+    /// Revokes an approved codehash. Oracles running it fail `require_approved_codehash` and
+    /// can't report prices starting from their next report.
+    #[payable]
+    pub fn revoke_codehash(&mut self, codehash: String) {
+        assert_one_yocto();
+        self.assert_admin();
+        let codehash = normalize_codehash(&codehash);
+        assert!(
+            self.approved_codehashes.remove(&codehash).is_some(),
+            "Codehash is not approved"
+        );
+        self.internal_log_owner_action("revoke_codehash", &codehash);
+    }
+
+    pub fn is_codehash_approved(&self, codehash: String) -> bool {
+        self.internal_is_codehash_approved(&codehash)
+    }
+
+    /// Returns a page of the approved codehashes, including the expired approvals that weren't
+    /// revoked.
+    pub fn get_approved_codehashes(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<String> {
+        self.approved_codehashes
+            .keys()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(u64::MAX) as usize)
+            .cloned()
+            .collect()
+    }
+
+    /// Requires the oracles to report with one of the approved checksums. Oracles without a
+    /// checksum, e.g. registered with `add_trusted_oracle`, can't report while it's on.
+    pub fn set_require_approved_checksum(&mut self, require_approved_checksum: bool) {
         self.assert_owner();
-        self.approved_codehashes.insert(codehash);
+        self.require_approved_checksum = require_approved_checksum;
+    }
+
+    pub fn approve_checksum(&mut self, checksum: String) {
+        self.assert_admin();
+        self.internal_log_owner_action("approve_checksum", &checksum);
+        self.approved_checksums.insert(checksum);
+    }
+
+    #[payable]
+    pub fn revoke_checksum(&mut self, checksum: String) {
+        assert_one_yocto();
+        self.assert_admin();
+        assert!(
+            self.approved_checksums.remove(&checksum),
+            "Checksum is not approved"
+        );
+        self.internal_log_owner_action("revoke_checksum", &checksum);
+    }
+
+    pub fn get_approved_checksums(&self) -> Vec<String> {
+        self.approved_checksums.iter().cloned().collect()
     }
 }
 
@@ -107,4 +874,638 @@ impl Contract {
             "Can only be called by the owner"
         );
     }
+
+    /// Requires the attached deposit to cover the storage added since `initial_storage_usage`
+    /// and refunds the excess to the predecessor.
+    pub(crate) fn internal_refund_storage_deposit(&self, initial_storage_usage: u64) {
+        let storage_cost = env::storage_byte_cost()
+            .saturating_mul(u128::from(env::storage_usage().saturating_sub(initial_storage_usage)));
+        let attached_deposit = env::attached_deposit();
+        assert!(
+            attached_deposit >= storage_cost,
+            "Not enough deposit to cover the storage, required {}",
+            storage_cost
+        );
+        let refund = attached_deposit.saturating_sub(storage_cost);
+        if !refund.is_zero() {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    pub fn assert_admin(&self) {
+        let predecessor_id = env::predecessor_account_id();
+        assert!(
+            predecessor_id == self.owner_id || self.admins.contains(&predecessor_id),
+            "Can only be called by the owner or an admin"
+        );
+    }
+    /// Points the aliases and the fallbacks targeting `old_id` at `new_id`.
+    fn internal_retarget_asset_id(&mut self, old_id: &AssetId, new_id: &AssetId) {
+        let alias_ids: Vec<AssetId> = self
+            .asset_aliases
+            .iter()
+            .filter(|(_, target_id)| *target_id == old_id)
+            .map(|(alias_id, _)| alias_id.clone())
+            .collect();
+        for alias_id in alias_ids {
+            self.asset_aliases.insert(alias_id, new_id.clone());
+        }
+        let asset_ids: Vec<AssetId> = self
+            .asset_fallbacks
+            .iter()
+            .filter(|(_, fallback_id)| *fallback_id == old_id)
+            .map(|(asset_id, _)| asset_id.clone())
+            .collect();
+        for asset_id in asset_ids {
+            self.asset_fallbacks.insert(asset_id, new_id.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::*;
+    use crate::*;
+    use near_sdk::test_utils::accounts;
+
+    #[test]
+    fn test_transfer_ownership() {
+        let mut contract = setup();
+        contract.propose_new_owner(accounts(1));
+        assert_eq!(contract.get_pending_owner(), Some(accounts(1)));
+        assert_eq!(contract.get_owner_id(), accounts(0));
+
+        set_context(accounts(1), ts(10));
+        contract.accept_ownership();
+        assert_eq!(contract.get_owner_id(), accounts(1));
+        assert_eq!(contract.get_pending_owner(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can only be called by the pending owner")]
+    fn test_accept_ownership_not_pending() {
+        let mut contract = setup();
+        contract.propose_new_owner(accounts(1));
+        set_context(accounts(2), ts(10));
+        contract.accept_ownership();
+    }
+
+    #[test]
+    fn test_admin_can_add_asset() {
+        let mut contract = setup();
+        contract.add_admin(accounts(1));
+        assert_eq!(contract.get_admins(), vec![accounts(1)]);
+
+        set_context_with_deposit(accounts(1), ts(10), NearToken::from_near(1));
+        contract.add_asset("wrap.near".to_string());
+        contract.approve_codehash("ab".repeat(32));
+        assert!(contract.get_asset("wrap.near".to_string()).is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "Can only be called by the owner")]
+    fn test_admin_cannot_add_admin() {
+        let mut contract = setup();
+        contract.add_admin(accounts(1));
+        set_context(accounts(1), ts(10));
+        contract.add_admin(accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Can only be called by the owner or an admin")]
+    fn test_removed_admin_cannot_add_asset() {
+        let mut contract = setup();
+        contract.add_admin(accounts(1));
+        contract.remove_admin(accounts(1));
+        set_context(accounts(1), ts(10));
+        contract.add_asset("wrap.near".to_string());
+    }
+
+    #[test]
+    fn test_add_asset_refunds_excess_deposit() {
+        let mut contract = setup();
+        set_context_with_deposit(accounts(0), ts(10), NearToken::from_near(1));
+        contract.add_asset("wrap.near".to_string());
+        let receipts = near_sdk::test_utils::get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, accounts(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough deposit to cover the storage")]
+    fn test_add_asset_requires_storage_deposit() {
+        let mut contract = setup();
+        contract.add_asset("wrap.near".to_string());
+    }
+
+    #[test]
+    fn test_rename_asset() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        contract.add_asset_ema("wrap.near".to_string(), 3600, None, None);
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+
+        set_context(accounts(0), ts(20));
+        contract.rename_asset("wrap.near".to_string(), "wnear.near".to_string(), Some(true), None);
+        assert!(contract.get_asset("wrap.near".to_string()).is_none());
+        assert_eq!(spot(&contract, "wnear.near"), Some(price(5, 0)));
+        assert_eq!(
+            contract.get_asset("wnear.near".to_string()).unwrap().emas[0].price,
+            Some(price(5, 0))
+        );
+
+        let data = contract.get_price_data(Some(vec!["wrap.near".to_string()]), None, None);
+        assert_eq!(data.prices[0].asset_id, "wrap.near");
+        assert_eq!(data.prices[0].price, Some(price(5, 0)));
+
+        contract.remove_asset_alias("wrap.near".to_string());
+        assert_eq!(spot(&contract, "wrap.near"), None);
+    }
+
+    #[test]
+    fn test_set_asset_alias() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wnear.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wnear.near", price(5, 0))]);
+
+        set_context(accounts(0), ts(10));
+        contract.set_asset_alias("wrap.near".to_string(), "wnear.near".to_string());
+        contract.set_asset_alias("old.wrap.near".to_string(), "wrap.near".to_string());
+        let data = contract.get_price_data(
+            Some(vec!["wrap.near".to_string(), "old.wrap.near".to_string()]),
+            None,
+            None,
+        );
+        assert_eq!(data.prices[0].asset_id, "wrap.near");
+        assert_eq!(data.prices[0].price, Some(price(5, 0)));
+        assert_eq!(data.prices[1].asset_id, "old.wrap.near");
+        assert_eq!(data.prices[1].price, Some(price(5, 0)));
+
+        let trace = contract.explain_asset_id("old.wrap.near".to_string());
+        assert_eq!(trace.steps[1], "alias of wnear.near");
+        assert_eq!(trace.source_asset_ids, vec!["wnear.near".to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Asset alias would create a cycle")]
+    fn test_set_asset_alias_rejects_cycle() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wnear.near");
+        contract.set_asset_alias("wrap.near".to_string(), "wnear.near".to_string());
+        contract.set_asset_alias("old.wrap.near".to_string(), "wrap.near".to_string());
+        contract.set_asset_alias("wrap.near".to_string(), "old.wrap.near".to_string());
+    }
+
+    #[test]
+    fn test_set_asset_fallback() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "usdc.primary");
+        add_test_asset(&mut contract, "usdc.secondary");
+        contract.set_asset_fallback("usdc.primary".to_string(), Some("usdc.secondary".to_string()));
+        report(&mut contract, &accounts(1), ts(10), &[("usdc.primary", price(100, 2))]);
+        report(&mut contract, &accounts(1), ts(100), &[("usdc.secondary", price(99, 2))]);
+
+        let data = contract.get_price_data(Some(vec!["usdc.primary".to_string()]), None, None);
+        assert_eq!(data.prices[0].price, Some(price(100, 2)));
+        assert_eq!(data.prices[0].source_asset_id, None);
+
+        set_context(accounts(0), ts(10 + contract.recency_duration_sec + 1));
+        let data = contract.get_price_data(Some(vec!["usdc.primary".to_string()]), None, None);
+        assert_eq!(data.prices[0].asset_id, "usdc.primary");
+        assert_eq!(data.prices[0].price, Some(price(99, 2)));
+        assert_eq!(data.prices[0].source_asset_id, Some("usdc.secondary".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Asset already exists")]
+    fn test_rename_asset_to_existing() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        add_test_asset(&mut contract, "wnear.near");
+        contract.rename_asset("wrap.near".to_string(), "wnear.near".to_string(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Asset ID is already an alias")]
+    fn test_rename_asset_to_alias() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        add_test_asset(&mut contract, "usdc.near");
+        contract.set_asset_alias("wnear.near".to_string(), "usdc.near".to_string());
+        contract.rename_asset("wrap.near".to_string(), "wnear.near".to_string(), None, None);
+    }
+
+    #[test]
+    fn test_rename_asset_replaces_alias() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        add_test_asset(&mut contract, "usdc.near");
+        contract.set_asset_alias("wnear.near".to_string(), "usdc.near".to_string());
+        contract.rename_asset("wrap.near".to_string(), "wnear.near".to_string(), None, Some(true));
+        assert!(contract.asset_aliases.is_empty());
+        assert!(contract.internal_get_asset(&"wnear.near".to_string()).is_some());
+    }
+
+    #[test]
+    fn test_remove_asset_cleans_up() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        add_test_asset(&mut contract, "usdc.near");
+        contract.enable_asset_history("wrap.near".to_string(), 10);
+        contract.set_asset_alias("old.wrap.near".to_string(), "wrap.near".to_string());
+        contract.set_asset_alias("older.wrap.near".to_string(), "old.wrap.near".to_string());
+        contract.set_asset_fallback("usdc.near".to_string(), Some("old.wrap.near".to_string()));
+        contract.set_asset_fallback("wrap.near".to_string(), Some("usdc.near".to_string()));
+
+        set_context(accounts(0), ts(0));
+        contract.remove_asset("wrap.near".to_string());
+        assert!(contract.asset_histories.get(&"wrap.near".to_string()).is_none());
+        assert!(contract.asset_aliases.is_empty());
+        assert!(contract.asset_fallbacks.is_empty());
+    }
+
+    #[test]
+    fn test_rename_asset_retargets_aliases_and_fallbacks() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        add_test_asset(&mut contract, "usdc.near");
+        contract.set_asset_alias("old.wrap.near".to_string(), "wrap.near".to_string());
+        contract.set_asset_fallback("usdc.near".to_string(), Some("wrap.near".to_string()));
+        contract.set_asset_fallback("wrap.near".to_string(), Some("usdc.near".to_string()));
+
+        set_context(accounts(0), ts(0));
+        contract.rename_asset("wrap.near".to_string(), "wnear.near".to_string(), None, None);
+        assert_eq!(contract.internal_resolve_asset_id("old.wrap.near"), "wnear.near");
+        assert_eq!(
+            contract.get_asset_fallback("usdc.near".to_string()),
+            Some("wnear.near".to_string())
+        );
+        assert_eq!(contract.get_asset_fallback("wrap.near".to_string()), None);
+        assert_eq!(
+            contract.get_asset_fallback("wnear.near".to_string()),
+            Some("usdc.near".to_string())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Asset ID is already an alias")]
+    fn test_add_asset_alias_id() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wnear.near");
+        contract.set_asset_alias("wrap.near".to_string(), "wnear.near".to_string());
+        add_test_asset(&mut contract, "wrap.near");
+    }
+
+    #[test]
+    #[should_panic(expected = "EMA for this period already exists")]
+    fn test_add_asset_ema_duplicate() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        contract.add_asset_ema("wrap.near".to_string(), 3600, None, None);
+        contract.add_asset_ema("wrap.near".to_string(), 3600, Some(100), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "EMA period must be positive")]
+    fn test_add_asset_ema_zero_period() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        contract.add_asset_ema("wrap.near".to_string(), 0, None, None);
+    }
+
+    #[test]
+    fn test_remove_asset_ema() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        contract.add_asset_ema("wrap.near".to_string(), 3600, None, None);
+        contract.add_asset_ema("wrap.near".to_string(), 600, None, None);
+        contract.remove_asset_ema("wrap.near".to_string(), 3600);
+        let asset = contract.get_asset("wrap.near".to_string()).unwrap();
+        assert_eq!(asset.emas.len(), 1);
+        assert_eq!(asset.emas[0].period_sec, 600);
+    }
+
+    #[test]
+    fn test_set_asset_ema_value() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        contract.add_asset_ema("wrap.near".to_string(), 86400, None, None);
+        set_context(accounts(0), ts(100));
+        contract.set_asset_ema_value("wrap.near".to_string(), 86400, price(52, 1), ts(90));
+        let data = contract.get_price_data(Some(vec!["wrap.near#86400".to_string()]), None, None);
+        assert_eq!(data.prices[0].price, Some(price(52, 1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "EMA already has recent data")]
+    fn test_set_asset_ema_value_recent_data() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        contract.add_asset_ema("wrap.near".to_string(), 86400, None, None);
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+        set_context(accounts(0), ts(100));
+        contract.set_asset_ema_value("wrap.near".to_string(), 86400, price(52, 1), ts(90));
+    }
+
+    #[test]
+    fn test_add_asset_ema_short_period_warning() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        contract.add_asset_ema("wrap.near".to_string(), 3600, None, None);
+        assert!(near_sdk::test_utils::get_logs().is_empty());
+        contract.add_asset_ema("wrap.near".to_string(), 600, None, None);
+        assert_eq!(
+            near_sdk::test_utils::get_logs(),
+            vec![
+                "Warning! EMA period 600 of wrap.near is shorter than its recency duration of \
+                 3600 seconds"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reset_oracle_stats() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+        report(&mut contract, &accounts(1), ts(20), &[("wrap.near", price(6, 0))]);
+        assert_eq!(contract.get_oracle(accounts(1)).unwrap().price_reports, 2);
+
+        set_context(accounts(0), ts(30));
+        contract.reset_oracle_stats(accounts(1), None);
+        let oracle = contract.get_oracle(accounts(1)).unwrap();
+        assert_eq!((oracle.price_reports, oracle.last_report), (0, ts(20)));
+
+        contract.reset_oracle_stats(accounts(1), Some(true));
+        let oracle = contract.get_oracle(accounts(1)).unwrap();
+        assert_eq!((oracle.price_reports, oracle.last_report, oracle.last_near_claim), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_reset_asset_emas() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        add_test_asset(&mut contract, "dai.near");
+        contract.add_asset_ema("wrap.near".to_string(), 86400, None, None);
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+        report(&mut contract, &accounts(1), ts(20), &[("wrap.near", price(10, 0))]);
+        let ema = contract.get_asset("wrap.near".to_string()).unwrap().emas[0].clone();
+        assert_eq!(ema.timestamp, ts(20));
+
+        set_context(accounts(0), ts(20));
+        contract.reset_asset_emas("wrap.near".to_string());
+        let ema = contract.get_asset("wrap.near".to_string()).unwrap().emas[0].clone();
+        assert_eq!((ema.period_sec, ema.timestamp, ema.price), (86400, 0, None));
+
+        report(&mut contract, &accounts(1), ts(30), &[("wrap.near", price(7, 0))]);
+        let ema = contract.get_asset("wrap.near".to_string()).unwrap().emas[0].clone();
+        assert_eq!((ema.timestamp, ema.price), (ts(30), Some(price(7, 0))));
+
+        set_context(accounts(0), ts(30));
+        assert_eq!(contract.reset_all_emas(None, None), 1);
+        assert_eq!(contract.reset_all_emas(Some(1), None), 0);
+        let ema = contract.get_asset("wrap.near".to_string()).unwrap().emas[0].clone();
+        assert_eq!(ema.price, None);
+    }
+
+    #[test]
+    fn test_asset_metadata() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        let metadata = AssetMetadata {
+            symbol: Some("wNEAR".to_string()),
+            token_decimals: Some(24),
+            description: Some("Wrapped NEAR".to_string()),
+        };
+        contract.set_asset_metadata("wrap.near".to_string(), Some(metadata.clone()));
+        let asset = contract.get_asset("wrap.near".to_string()).unwrap();
+        assert_eq!(asset.metadata, Some(metadata));
+    }
+
+    #[test]
+    #[should_panic(expected = "Token decimals can't exceed 77")]
+    fn test_asset_metadata_invalid_decimals() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        contract.set_asset_metadata(
+            "wrap.near".to_string(),
+            Some(AssetMetadata {
+                symbol: None,
+                token_decimals: Some(78),
+                description: None,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_remove_oracle_at_keeps_quorum() {
+        let mut contract = setup();
+        for i in 1..=3 {
+            add_test_oracle(&mut contract, &accounts(i));
+        }
+        add_test_asset(&mut contract, "wrap.near");
+        contract.remove_oracle_at(accounts(3), ts(100));
+        report(&mut contract, &accounts(1), ts(60), &[("wrap.near", price(5, 0))]);
+        // 3 oracles still count, so 2 fresh reports are required.
+        assert_eq!(spot(&contract, "wrap.near"), None);
+
+        set_context(accounts(0), ts(100));
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(5, 0)));
+
+        contract.finalize_oracle_removal(accounts(3));
+        assert!(contract.get_oracle(accounts(3)).is_none());
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(5, 0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "E_ORACLE_REMOVAL: Oracle is scheduled for removal")]
+    fn test_remove_oracle_at_blocks_reports() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        contract.remove_oracle_at(accounts(1), ts(100));
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Deactivation must be in the future")]
+    fn test_remove_oracle_at_past_timestamp() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        set_context(accounts(0), ts(100));
+        contract.remove_oracle_at(accounts(1), ts(100));
+    }
+
+    #[test]
+    fn test_revoke_oracle_purges_reports() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_oracle(&mut contract, &accounts(2));
+        add_test_asset(&mut contract, "wrap.near");
+        add_test_asset(&mut contract, "dai.near");
+        report(
+            &mut contract,
+            &accounts(1),
+            ts(10),
+            &[("wrap.near", price(100, 0)), ("dai.near", price(1, 0))],
+        );
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(100, 0)));
+
+        set_context(accounts(0), ts(20));
+        contract.revoke_oracle(accounts(1), None);
+        assert!(contract.get_oracle(accounts(1)).is_none());
+        assert_eq!(spot(&contract, "wrap.near"), None);
+        assert_eq!(spot(&contract, "dai.near"), None);
+        assert!(contract
+            .get_asset("wrap.near".to_string())
+            .unwrap()
+            .reports
+            .is_empty());
+    }
+
+    #[test]
+    fn test_codehash_approval_before_expiry() {
+        let mut contract = setup();
+        contract.approve_codehash_until(CODEHASH.to_string(), ts(100));
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(50), &[("wrap.near", price(1, 0))]);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(1, 0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "is not approved")]
+    fn test_codehash_approval_after_expiry() {
+        let mut contract = setup();
+        contract.approve_codehash_until(CODEHASH.to_string(), ts(100));
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(100), &[("wrap.near", price(1, 0))]);
+    }
+
+    #[test]
+    fn test_approve_codehash_normalizes_case() {
+        let mut contract = setup();
+        contract.approve_codehash("AB".repeat(32));
+        assert!(contract.is_codehash_approved("ab".repeat(32)));
+        assert!(contract.is_codehash_approved("Ab".repeat(32)));
+    }
+
+    #[test]
+    fn test_get_approved_codehashes() {
+        let mut contract = setup();
+        contract.approve_codehash("aa".repeat(32));
+        contract.approve_codehash("BB".repeat(32));
+        assert_eq!(
+            contract.get_approved_codehashes(None, None),
+            vec![CODEHASH.to_string(), "aa".repeat(32), "bb".repeat(32)]
+        );
+        assert_eq!(contract.get_approved_codehashes(Some(1), Some(1)), vec!["aa".repeat(32)]);
+        assert!(contract.get_approved_codehashes(Some(3), None).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Codehash must be 64 hex characters")]
+    fn test_approve_codehash_rejects_malformed() {
+        let mut contract = setup();
+        contract.approve_codehash("abcd".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "E_CODEHASH: Oracle bob codehash")]
+    fn test_revoke_codehash() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(1, 0))]);
+
+        set_context(accounts(0), ts(20));
+        contract.revoke_codehash(CODEHASH.to_string());
+        assert!(!contract.is_codehash_approved(CODEHASH.to_string()));
+        report(&mut contract, &accounts(1), ts(30), &[("wrap.near", price(1, 0))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Maximum number of oracles reached")]
+    fn test_max_oracles_rejects_beyond_cap() {
+        let mut contract = setup();
+        contract.set_max_oracles(Some(2));
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_oracle(&mut contract, &accounts(2));
+        add_test_oracle(&mut contract, &accounts(3));
+    }
+
+    #[test]
+    fn test_max_oracles_removal_frees_slot() {
+        let mut contract = setup();
+        contract.set_max_oracles(Some(2));
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_oracle(&mut contract, &accounts(2));
+        contract.remove_oracle(accounts(1));
+        add_test_oracle(&mut contract, &accounts(3));
+        assert!(contract.get_oracle(accounts(1)).is_none());
+        assert!(contract.get_oracle(accounts(3)).is_some());
+    }
+
+    #[test]
+    fn test_remove_oracle_keeps_reports() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+
+        set_context(accounts(0), ts(20));
+        let oracle = contract.remove_oracle(accounts(1)).unwrap();
+        assert_eq!(oracle.last_report, ts(10));
+        assert!(contract.get_oracle(accounts(1)).is_none());
+        assert!(contract.remove_oracle(accounts(1)).is_none());
+        assert_eq!(contract.get_asset("wrap.near".to_string()).unwrap().reports.len(), 1);
+
+        contract.clean_oracle_data(accounts(1), vec!["wrap.near".to_string()]);
+        assert!(contract.get_asset("wrap.near".to_string()).unwrap().reports.is_empty());
+    }
+
+    #[test]
+    fn test_prune_asset() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_oracle(&mut contract, &accounts(2));
+        add_test_asset(&mut contract, "wrap.near");
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+        report(&mut contract, &accounts(2), ts(5000), &[("wrap.near", price(6, 0))]);
+
+        set_context(accounts(0), ts(5000));
+        assert_eq!(contract.prune_asset("wrap.near".to_string(), 3600), 1);
+        let reports = contract.get_asset_reports("wrap.near".to_string(), Some(true));
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].oracle_id, accounts(2));
+        assert_eq!(contract.prune_asset("wrap.near".to_string(), 3600), 0);
+    }
+
+    #[test]
+    fn test_add_trusted_oracle() {
+        let mut contract = setup();
+        add_test_asset(&mut contract, "wrap.near");
+        set_context_with_deposit(accounts(0), ts(0), NearToken::from_near(1));
+        contract.add_trusted_oracle(accounts(1), CODEHASH.to_uppercase());
+        assert_eq!(
+            contract.get_oracle(accounts(1)).unwrap().codehash,
+            Some(CODEHASH.to_string())
+        );
+        report(&mut contract, &accounts(1), ts(10), &[("wrap.near", price(5, 0))]);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(5, 0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Codehash is not approved")]
+    fn test_add_trusted_oracle_requires_approved_codehash() {
+        let mut contract = setup();
+        contract.add_trusted_oracle(accounts(1), "ab".repeat(32));
+    }
 }
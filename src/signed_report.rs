@@ -0,0 +1,238 @@
+use crate::*;
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::CurveType;
+use near_sdk_macros::NearSchema;
+
+/// Prices of an oracle submitted by a relayer on its behalf, see `report_prices_signed`.
+#[derive(Serialize, Deserialize, NearSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SignedOracleReport {
+    pub oracle_id: AccountId,
+    pub prices: Vec<AssetPrice>,
+    /// When the oracle signed the report. Used as the observation time of the prices that
+    /// don't have one.
+    pub timestamp: Timestamp,
+    /// Must be greater than the last nonce used by the oracle.
+    pub nonce: u64,
+    /// ed25519 signature of the JSON serialized `SignedReportMessage`.
+    pub signature: Base64VecU8,
+}
+
+/// The message signed by the oracle. It includes the contract ID, so a report can't be
+/// replayed on another deployment.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SignedReportMessage<'a> {
+    pub contract_id: &'a AccountId,
+    pub oracle_id: &'a AccountId,
+    pub prices: &'a [AssetPrice],
+    pub timestamp: Timestamp,
+    pub nonce: u64,
+}
+
+impl SignedOracleReport {
+    pub fn message(&self, contract_id: &AccountId) -> Vec<u8> {
+        serde_json::to_vec(&SignedReportMessage {
+            contract_id,
+            oracle_id: &self.oracle_id,
+            prices: &self.prices,
+            timestamp: self.timestamp,
+            nonce: self.nonce,
+        })
+        .unwrap()
+    }
+}
+
+#[near]
+impl Contract {
+    /// Sets the ed25519 key the calling oracle signs relayed reports with. `None` stops
+    /// accepting relayed reports for the oracle.
+    pub fn set_oracle_public_key(&mut self, public_key: Option<PublicKey>) {
+        let oracle_id = env::predecessor_account_id();
+        let mut oracle = self.internal_get_oracle(&oracle_id).expect("Not an oracle");
+        if let Some(public_key) = public_key.as_ref() {
+            assert!(
+                public_key.curve_type() == CurveType::ED25519,
+                "Only ed25519 keys are supported"
+            );
+        }
+        oracle.public_key = public_key;
+        self.internal_set_oracle(&oracle_id, oracle);
+    }
+
+    pub fn get_oracle_nonce(&self, account_id: AccountId) -> u64 {
+        self.oracle_nonces.get(&account_id).copied().unwrap_or(0)
+    }
+
+    /// Applies the reports of several oracles in a single transaction, e.g. submitted by a
+    /// relayer. Each report must be signed by the oracle's key and use a fresh nonce. The
+    /// reports are applied as if the oracles called `report_prices` without claiming NEAR.
+    pub fn report_prices_signed(&mut self, reports: Vec<SignedOracleReport>) {
+        assert!(!reports.is_empty(), "{}: No reports", E_EMPTY_REPORT);
+        let contract_id = env::current_account_id();
+        for report in reports {
+            let oracle = self
+                .internal_get_oracle(&report.oracle_id)
+                .unwrap_or_else(|| env::panic_str(&format!("{}: Not an oracle", E_NOT_ORACLE)));
+            let public_key: [u8; 32] = oracle
+                .public_key
+                .as_ref()
+                .unwrap_or_else(|| {
+                    env::panic_str(&format!("{}: Oracle has no public key", E_SIGNATURE))
+                })
+                .as_bytes()[1..]
+                .try_into()
+                .unwrap();
+            let signature: [u8; 64] =
+                report.signature.0.as_slice().try_into().unwrap_or_else(|_| {
+                    env::panic_str(&format!("{}: Invalid signature length", E_SIGNATURE))
+                });
+            assert!(
+                env::ed25519_verify(&signature, &report.message(&contract_id), &public_key),
+                "{}: Invalid signature of {}",
+                E_SIGNATURE,
+                report.oracle_id
+            );
+            assert!(
+                report.nonce > self.get_oracle_nonce(report.oracle_id.clone()),
+                "{}: Nonce of {} was already used",
+                E_NONCE,
+                report.oracle_id
+            );
+            self.oracle_nonces
+                .insert(report.oracle_id.clone(), report.nonce);
+
+            let prices = report
+                .prices
+                .into_iter()
+                .map(|price| AssetPrice {
+                    observed_at: price.observed_at.or(Some(report.timestamp)),
+                    ..price
+                })
+                .collect();
+            self.internal_report_prices(report.oracle_id, prices, false, false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::*;
+    use crate::*;
+    use ed25519_dalek::{Signer, SigningKey};
+    use near_sdk::json_types::Base64VecU8;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::CurveType;
+
+    fn add_signing_oracle(contract: &mut Contract, account_id: &AccountId, seed: u8) -> SigningKey {
+        add_test_oracle(contract, account_id);
+        let signing_key = SigningKey::from_bytes(&[seed; 32]);
+        let public_key = PublicKey::from_parts(
+            CurveType::ED25519,
+            signing_key.verifying_key().to_bytes().to_vec(),
+        )
+        .unwrap();
+        let timestamp = env::block_timestamp();
+        set_context(account_id.clone(), timestamp);
+        contract.set_oracle_public_key(Some(public_key));
+        set_context(contract.owner_id.clone(), timestamp);
+        signing_key
+    }
+
+    fn signed_report(
+        signing_key: &SigningKey,
+        oracle_id: &AccountId,
+        prices: &[(&str, Price)],
+        timestamp: Timestamp,
+        nonce: u64,
+    ) -> SignedOracleReport {
+        let mut report = SignedOracleReport {
+            oracle_id: oracle_id.clone(),
+            prices: asset_prices(prices),
+            timestamp,
+            nonce,
+            signature: Base64VecU8(vec![]),
+        };
+        let signature = signing_key.sign(&report.message(&env::current_account_id()));
+        report.signature = Base64VecU8(signature.to_bytes().to_vec());
+        report
+    }
+
+    #[test]
+    fn test_report_prices_signed() {
+        let mut contract = setup();
+        let bob_key = add_signing_oracle(&mut contract, &accounts(1), 1);
+        let charlie_key = add_signing_oracle(&mut contract, &accounts(2), 2);
+        add_test_asset(&mut contract, "wrap.near");
+
+        set_context(accounts(3), ts(20));
+        contract.report_prices_signed(vec![
+            signed_report(
+                &bob_key,
+                &accounts(1),
+                &[("wrap.near", price(5, 0))],
+                ts(10),
+                1,
+            ),
+            signed_report(
+                &charlie_key,
+                &accounts(2),
+                &[("wrap.near", price(7, 0))],
+                ts(20),
+                1,
+            ),
+        ]);
+        let reports = contract.get_asset_reports("wrap.near".to_string(), None);
+        let summary: Vec<_> = reports
+            .iter()
+            .map(|rp| (rp.oracle_id.clone(), rp.timestamp, rp.price))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (accounts(1), ts(10), price(5, 0)),
+                (accounts(2), ts(20), price(7, 0))
+            ]
+        );
+        assert_eq!(contract.get_oracle_nonce(accounts(1)), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_NONCE: Nonce of bob was already used")]
+    fn test_report_prices_signed_replayed_nonce() {
+        let mut contract = setup();
+        let bob_key = add_signing_oracle(&mut contract, &accounts(1), 1);
+        add_test_asset(&mut contract, "wrap.near");
+
+        set_context(accounts(3), ts(20));
+        let report = || {
+            signed_report(
+                &bob_key,
+                &accounts(1),
+                &[("wrap.near", price(5, 0))],
+                ts(10),
+                1,
+            )
+        };
+        contract.report_prices_signed(vec![report()]);
+        contract.report_prices_signed(vec![report()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_SIGNATURE: Invalid signature of bob")]
+    fn test_report_prices_signed_wrong_key() {
+        let mut contract = setup();
+        add_signing_oracle(&mut contract, &accounts(1), 1);
+        let charlie_key = add_signing_oracle(&mut contract, &accounts(2), 2);
+        add_test_asset(&mut contract, "wrap.near");
+
+        set_context(accounts(3), ts(20));
+        contract.report_prices_signed(vec![signed_report(
+            &charlie_key,
+            &accounts(1),
+            &[("wrap.near", price(5, 0))],
+            ts(10),
+            1,
+        )]);
+    }
+}
@@ -5,7 +5,47 @@ impl Contract {
     #[private]
     #[init(ignore_state)]
     pub fn migrate_state() -> Self {
-        env::state_read().unwrap()
+        let contract = Self::internal_migrate_state();
+        contract.assert_valid_migrated_state();
+        contract
+    }
+
+    /// Same as `migrate_state`, but overrides the recency duration, e.g. when the stored one
+    /// is zero.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate_state_with_recency(recency_duration_sec: DurationSec) -> Self {
+        let mut contract = Self::internal_migrate_state();
+        contract.recency_duration_sec = recency_duration_sec;
+        contract.assert_valid_migrated_state();
+        contract
+    }
+
+    /// Upgrades the stored asset to the current version. Returns false if it was already
+    /// migrated. Allows migrating a large asset map incrementally.
+    pub fn migrate_asset(&mut self, asset_id: AssetId) -> bool {
+        self.assert_admin();
+        let v_asset = self.assets.get(&asset_id).expect("Missing an asset");
+        if matches!(v_asset, VAsset::Current(_)) {
+            return false;
+        }
+        let asset = self.internal_get_asset(&asset_id).unwrap();
+        self.internal_set_asset(&asset_id, asset);
+        true
+    }
+
+    /// Returns the IDs of the assets that are not migrated yet within the given page of the
+    /// asset map. The page is applied before filtering, so it may return fewer IDs than `limit`.
+    pub fn get_unmigrated_assets(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<AssetId> {
+        let from_index = from_index.unwrap_or(0) as usize;
+        let limit = limit.map_or(usize::MAX, |limit| limit as usize);
+        self.assets
+            .iter()
+            .skip(from_index)
+            .take(limit)
+            .filter(|(_, v_asset)| !matches!(v_asset, VAsset::Current(_)))
+            .map(|(asset_id, _)| asset_id.clone())
+            .collect()
     }
 
     /// Returns semver of this contract.
@@ -14,5 +54,206 @@ impl Contract {
     }
 }
 
+impl Contract {
+    /// Converts the root state of the baseline contract. The approved codehashes move from
+    /// the baseline set to the current map, as approvals that never expire.
+    fn internal_migrate_state() -> Self {
+        let ContractV0 {
+            oracles,
+            assets,
+            recency_duration_sec,
+            owner_id,
+            near_claim_amount,
+            approved_codehashes: mut old_approved_codehashes,
+            worker_by_account_id,
+        } = env::state_read().expect("Missing the contract state");
+        let mut approved_codehashes = IterableMap::new(StorageKey::ApprovedCodehashes);
+        for codehash in old_approved_codehashes.iter() {
+            approved_codehashes.insert(codehash.clone(), CodehashApproval::new(None));
+        }
+        old_approved_codehashes.clear();
+        Self {
+            oracles,
+            assets,
+            recency_duration_sec,
+            owner_id,
+            near_claim_amount,
+            approved_codehashes,
+            worker_by_account_id,
+            max_oracles: None,
+            asset_histories: LookupMap::new(StorageKey::AssetHistories),
+            oracle_deactivations: IterableMap::new(StorageKey::OracleDeactivations),
+            asset_aliases: IterableMap::new(StorageKey::AssetAliases),
+            min_report_interval_sec: 0,
+            pending_owner: None,
+            admins: IterableSet::new(StorageKey::Admins),
+            report_prune_threshold_sec: None,
+            near_claim_cooldown_sec: DEFAULT_NEAR_CLAIM_COOLDOWN_SEC,
+            approved_checksums: IterableSet::new(StorageKey::ApprovedChecksums),
+            require_approved_checksum: false,
+            oracle_nonces: LookupMap::new(StorageKey::OracleNonces),
+            max_report_batch: DEFAULT_MAX_REPORT_BATCH,
+            asset_fallbacks: IterableMap::new(StorageKey::AssetFallbacks),
+            max_oracle_call_msg_len: DEFAULT_MAX_ORACLE_CALL_MSG_LEN,
+            total_bonded: NearToken::from_yoctonear(0),
+            removed_oracle_bonds: LookupMap::new(StorageKey::RemovedOracleBonds),
+            owner_actions: LookupMap::new(StorageKey::OwnerActions),
+            num_owner_actions: 0,
+            global_min_reports: 0,
+            oracle_ids_by_index: Vector::new(StorageKey::OracleIdsByIndex),
+            oracle_indices: LookupMap::new(StorageKey::OracleIndices),
+            claim_safety_margin: DEFAULT_CLAIM_SAFETY_MARGIN,
+            max_price_data_assets: DEFAULT_MAX_PRICE_DATA_ASSETS,
+        }
+    }
+
+    /// Panics if the migrated state would make every price stale.
+    fn assert_valid_migrated_state(&self) {
+        assert!(
+            self.recency_duration_sec > 0,
+            "Recency duration can't be zero after the migration, use migrate_state_with_recency"
+        );
+    }
+}
+
 // Note: Low-level upgrade functionality has been removed for near-sdk 5.x compatibility
 // Contract upgrades should be handled through standard deployment mechanisms
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::*;
+    use crate::*;
+    use near_sdk::test_utils::accounts;
+
+    /// Writes an empty root state of the baseline contract.
+    fn write_state_v0(recency_duration_sec: DurationSec) {
+        set_context(accounts(0), ts(0));
+        env::state_write(&ContractV0 {
+            oracles: UnorderedMap::new(StorageKey::Oracles),
+            assets: UnorderedMap::new(StorageKey::Assets),
+            recency_duration_sec,
+            owner_id: accounts(0),
+            near_claim_amount: NearToken::from_near(1),
+            approved_codehashes: IterableSet::new(b"a"),
+            worker_by_account_id: IterableMap::new(b"b"),
+        });
+    }
+
+    #[test]
+    fn test_migrate_asset() {
+        let mut contract = setup();
+        add_test_oracle(&mut contract, &accounts(1));
+        add_test_asset(&mut contract, "dai.near");
+        let report = ReportV0 {
+            oracle_id: accounts(1),
+            timestamp: ts(10),
+            price: PriceV0 {
+                multiplier: 1,
+                decimals: 0,
+            },
+        };
+        contract.assets.insert(
+            "wrap.near".to_string(),
+            VAsset::V0(AssetV0 {
+                reports: vec![report.clone()],
+            }),
+        );
+        contract.assets.insert(
+            "usdc.near".to_string(),
+            VAsset::V1(AssetV1 {
+                reports: vec![report],
+                emas: vec![AssetEmaV0 {
+                    period_sec: 3600,
+                    timestamp: ts(10),
+                    price: Some(PriceV0 {
+                        multiplier: 2,
+                        decimals: 0,
+                    }),
+                }],
+            }),
+        );
+        assert_eq!(
+            contract.get_unmigrated_assets(None, None),
+            vec!["wrap.near", "usdc.near"]
+        );
+        assert!(contract.get_unmigrated_assets(Some(0), Some(1)).is_empty());
+
+        for asset_id in ["wrap.near", "usdc.near"] {
+            assert!(contract.migrate_asset(asset_id.to_string()));
+            assert!(!contract.migrate_asset(asset_id.to_string()));
+            assert!(matches!(contract.assets.get(asset_id), Some(VAsset::Current(_))));
+            assert_eq!(spot(&contract, asset_id), Some(price(1, 0)));
+        }
+        assert!(contract.get_unmigrated_assets(None, None).is_empty());
+        let ema = &contract.get_asset("usdc.near".to_string()).unwrap().emas[0];
+        assert_eq!((ema.period_sec, ema.price, ema.alpha_bps), (3600, Some(price(2, 0)), None));
+    }
+
+    #[test]
+    fn test_migrate_state() {
+        set_context(accounts(0), ts(0));
+        {
+            let mut contract = ContractV0 {
+                oracles: UnorderedMap::new(StorageKey::Oracles),
+                assets: UnorderedMap::new(StorageKey::Assets),
+                recency_duration_sec: 600,
+                owner_id: accounts(0),
+                near_claim_amount: NearToken::from_near(1),
+                approved_codehashes: IterableSet::new(b"a"),
+                worker_by_account_id: IterableMap::new(b"b"),
+            };
+            contract.oracles.insert(
+                accounts(1),
+                VOracle::V1(OracleV1 {
+                    last_report: ts(0),
+                    price_reports: 1,
+                    last_near_claim: 0,
+                    codehash: Some(CODEHASH.to_string()),
+                    checksum: None,
+                }),
+            );
+            contract.assets.insert(
+                "wrap.near".to_string(),
+                VAsset::V1(AssetV1 {
+                    reports: vec![ReportV0 {
+                        oracle_id: accounts(1),
+                        timestamp: ts(0),
+                        price: PriceV0 {
+                            multiplier: 5,
+                            decimals: 0,
+                        },
+                    }],
+                    emas: vec![],
+                }),
+            );
+            contract.approved_codehashes.insert(CODEHASH.to_string());
+            env::state_write(&contract);
+        }
+
+        let contract = Contract::migrate_state();
+        assert_eq!(contract.owner_id, accounts(0));
+        assert_eq!(contract.recency_duration_sec, 600);
+        assert_eq!(contract.near_claim_amount, NearToken::from_near(1));
+        assert_eq!(contract.max_report_batch, DEFAULT_MAX_REPORT_BATCH);
+        assert!(contract.is_codehash_approved(CODEHASH.to_string()));
+        assert!(IterableSet::<String>::new(b"a").is_empty());
+        let oracle = contract.get_oracle(accounts(1)).unwrap();
+        assert_eq!(oracle.codehash.as_deref(), Some(CODEHASH));
+        assert!(oracle.active);
+        assert_eq!(spot(&contract, "wrap.near"), Some(price(5, 0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Recency duration can't be zero after the migration")]
+    fn test_migrate_state_rejects_zero_recency() {
+        write_state_v0(0);
+        Contract::migrate_state();
+    }
+
+    #[test]
+    fn test_migrate_state_with_recency() {
+        write_state_v0(0);
+        let contract = Contract::migrate_state_with_recency(600);
+        assert_eq!(contract.recency_duration_sec, 600);
+    }
+}
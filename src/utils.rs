@@ -2,8 +2,8 @@ use crate::*;
 use std::cmp::Ordering;
 use near_sdk_macros::NearSchema;
 
-const MAX_U128_DECIMALS: u8 = 38;
-const MAX_VALID_DECIMALS: u8 = 77;
+pub(crate) const MAX_U128_DECIMALS: u8 = 38;
+pub(crate) const MAX_VALID_DECIMALS: u8 = 77;
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Copy, NearSchema)]
 #[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
@@ -11,6 +11,9 @@ const MAX_VALID_DECIMALS: u8 = 77;
 pub struct Price {
     pub multiplier: u128,
     pub decimals: u8,
+    /// Only allowed for assets flagged as `signed`, e.g. funding rates.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub negative: bool,
 }
 
 // 5 NEAR = 5 * 10**24 "wrap.near"
@@ -23,44 +26,169 @@ pub struct Price {
 
 impl Price {
     pub fn assert_valid(&self) {
-        assert!(self.decimals <= MAX_VALID_DECIMALS);
+        assert!(
+            self.decimals <= MAX_VALID_DECIMALS,
+            "{}: Price decimals can't exceed {}",
+            E_INVALID_PRICE,
+            MAX_VALID_DECIMALS
+        );
     }
-}
 
-impl PartialEq<Self> for Price {
-    fn eq(&self, other: &Self) -> bool {
-        self.partial_cmp(other) == Some(Ordering::Equal)
+    /// Returns true if the multiplier is zero, regardless of the sign.
+    pub fn is_zero(&self) -> bool {
+        self.multiplier == 0
     }
-}
 
-impl PartialOrd for Price {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    /// Returns true if the price is below zero. A negative zero is zero.
+    pub fn is_negative(&self) -> bool {
+        self.negative && !self.is_zero()
+    }
+
+    /// Converts a raw token amount into its value with `target_decimals`, following the
+    /// examples above. The price decimals already include the token decimals, so
+    /// `token_decimals` only guards against mismatched inputs: if it's larger than the price
+    /// decimals, `None` is returned. Returns `None` on overflow.
+    pub fn to_amount(
+        &self,
+        raw_token_amount: u128,
+        token_decimals: u8,
+        target_decimals: u8,
+    ) -> Option<u128> {
+        if token_decimals > self.decimals {
+            return None;
+        }
+        let value = raw_token_amount.checked_mul(self.multiplier)?;
+        if target_decimals >= self.decimals {
+            value.checked_mul(10u128.checked_pow((target_decimals - self.decimals) as u32)?)
+        } else {
+            Some(
+                10u128
+                    .checked_pow((self.decimals - target_decimals) as u32)
+                    .map_or(0, |divisor| value / divisor),
+            )
+        }
+    }
+
+    /// Returns the approximate value of the price, e.g. for statistics on the reports.
+    pub fn to_f64(&self) -> f64 {
+        let value = self.multiplier as f64 / 10f64.powi(self.decimals as i32);
+        if self.negative {
+            -value
+        } else {
+            value
+        }
+    }
+
+    /// Returns the same price expressed with the given decimals. Scaling down truncates.
+    /// Returns `None` on overflow.
+    pub fn rescale(&self, decimals: u8) -> Option<Price> {
+        let multiplier = if decimals >= self.decimals {
+            self.multiplier
+                .checked_mul(10u128.checked_pow((decimals - self.decimals) as u32)?)?
+        } else {
+            10u128
+                .checked_pow((self.decimals - decimals) as u32)
+                .map_or(0, |divisor| self.multiplier / divisor)
+        };
+        Some(Price {
+            multiplier,
+            decimals,
+            negative: self.negative,
+        })
+    }
+
+    /// Returns this price denominated in `quote` instead of USD, expressed with the given
+    /// decimals. Both prices are per raw token unit, so is the result. Returns `None` if the
+    /// quote is zero or on overflow.
+    pub fn checked_div(&self, quote: &Price, decimals: u8) -> Option<Price> {
+        if quote.is_zero() {
+            return None;
+        }
+        // self / quote = (m1 / 10^d1) / (m2 / 10^d2) = m1 * 10^(decimals + d2 - d1) / m2
+        let exponent = i32::from(decimals) + i32::from(quote.decimals) - i32::from(self.decimals);
+        let scale = 10u128.checked_pow(exponent.unsigned_abs())?;
+        let multiplier = if exponent >= 0 {
+            self.multiplier.checked_mul(scale)? / quote.multiplier
+        } else {
+            self.multiplier / quote.multiplier.checked_mul(scale)?
+        };
+        Some(Price {
+            multiplier,
+            decimals,
+            negative: self.is_negative() != quote.is_negative(),
+        })
+    }
+
+    /// Returns the lowest and the highest prices within `bps` basis points of this price.
+    /// Both bounds carry 4 extra decimals to stay exact.
+    pub fn deviation_band(&self, bps: u32) -> Option<(Price, Price)> {
+        let decimals = self.decimals.checked_add(4)?;
+        let lower = self
+            .multiplier
+            .checked_mul(10000u128.saturating_sub(u128::from(bps)))?;
+        let upper = self.multiplier.checked_mul(10000 + u128::from(bps))?;
+        let lower = Price {
+            multiplier: lower,
+            decimals,
+            negative: self.negative,
+        };
+        let upper = Price {
+            multiplier: upper,
+            decimals,
+            negative: self.negative,
+        };
+        if self.negative {
+            Some((upper, lower))
+        } else {
+            Some((lower, upper))
+        }
+    }
+
+    /// Compares the absolute values of the prices.
+    fn cmp_magnitude(&self, other: &Self) -> Ordering {
         if self.decimals < other.decimals {
-            return Some(other.cmp(self).reverse());
+            return other.cmp_magnitude(self).reverse();
         }
 
         let decimals_diff = self.decimals - other.decimals;
 
         if decimals_diff > MAX_U128_DECIMALS {
-            return Some(Ordering::Less);
+            return Ordering::Less;
         }
 
         if let Some(om) = other
             .multiplier
             .checked_mul(10u128.pow(decimals_diff as u32))
         {
-            Some(self.multiplier.cmp(&om))
+            self.multiplier.cmp(&om)
         } else {
-            Some(Ordering::Less)
+            Ordering::Less
         }
     }
 }
 
+impl PartialEq<Self> for Price {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Eq for Price {}
 
 impl Ord for Price {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+        match (self.is_negative(), other.is_negative()) {
+            (false, false) => self.cmp_magnitude(other),
+            (true, true) => self.cmp_magnitude(other).reverse(),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
     }
 }
 
@@ -79,10 +207,26 @@ where
     VV: BorshSerialize + BorshDeserialize,
     V: From<VV> + for<'a> From<&'a VV>,
 {
-    let keys: Vec<K> = m.keys().cloned().collect();
+    filtered_unordered_map_pagination(m, |_| true, from_index, limit)
+}
+
+/// Same as `unordered_map_pagination`, but the index and the limit apply to the keys passing
+/// the filter.
+pub(crate) fn filtered_unordered_map_pagination<K, VV, V>(
+    m: &UnorderedMap<K, VV>,
+    filter: impl Fn(&K) -> bool,
+    from_index: Option<u64>,
+    limit: Option<u64>,
+) -> Vec<(K, V)>
+where
+    K: BorshSerialize + BorshDeserialize + Clone + Ord,
+    VV: BorshSerialize + BorshDeserialize,
+    V: From<VV> + for<'a> From<&'a VV>,
+{
+    let keys: Vec<K> = m.keys().filter(|&key| filter(key)).cloned().collect();
     let from_index = from_index.unwrap_or(0) as usize;
     let limit = limit.unwrap_or(keys.len() as u64) as usize;
-    let end_index = std::cmp::min(keys.len(), from_index + limit);
+    let end_index = std::cmp::min(keys.len(), from_index.saturating_add(limit));
     
     (from_index..end_index)
         .filter_map(|index| {
@@ -92,3 +236,139 @@ where
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Price, MAX_U128_DECIMALS, MAX_VALID_DECIMALS};
+
+    const NEAR: Price = Price {
+        multiplier: 1000,
+        decimals: 26,
+        negative: false,
+    };
+    const DAI: Price = Price {
+        multiplier: 101,
+        decimals: 20,
+        negative: false,
+    };
+
+    #[test]
+    fn test_to_amount() {
+        let five_near = 5 * 10u128.pow(24);
+        let fifty_dai = 50 * 10u128.pow(18);
+        assert_eq!(NEAR.to_amount(five_near, 24, 18), Some(50 * 10u128.pow(18)));
+        assert_eq!(DAI.to_amount(fifty_dai, 18, 18), Some(505 * 10u128.pow(17)));
+        assert_eq!(NEAR.to_amount(five_near, 24, 6), Some(50 * 10u128.pow(6)));
+        assert_eq!(NEAR.to_amount(five_near, 24, 30), Some(50 * 10u128.pow(30)));
+    }
+
+    #[test]
+    fn test_to_amount_overflow() {
+        assert_eq!(NEAR.to_amount(u128::MAX, 24, 18), None);
+        assert_eq!(NEAR.to_amount(10u128.pow(24), 24, 77), None);
+        assert_eq!(NEAR.to_amount(10u128.pow(24), 27, 18), None);
+    }
+
+    #[test]
+    fn test_rescale() {
+        let price = NEAR.rescale(30).unwrap();
+        assert_eq!((price.multiplier, price.decimals), (10_000_000, 30));
+        let price = NEAR.rescale(24).unwrap();
+        assert_eq!((price.multiplier, price.decimals), (10, 24));
+        assert_eq!(NEAR.rescale(77), None);
+    }
+
+    #[test]
+    fn test_checked_div() {
+        let usdc = Price {
+            multiplier: 10001,
+            decimals: 10,
+            negative: false,
+        };
+        // 10 USD per NEAR and 1.0001 USD per USDC, so 1 NEAR is 9.999 USDC, i.e. 10**24 yocto
+        // is 9999000 raw USDC units.
+        let price = NEAR.checked_div(&usdc, 24).unwrap();
+        assert_eq!((price.multiplier, price.decimals), (9999000, 24));
+        let price = NEAR.checked_div(&usdc, 28).unwrap();
+        assert_eq!(price.multiplier, 99990000999);
+        assert_eq!(NEAR.checked_div(&usdc, 8).unwrap().multiplier, 0);
+        assert_eq!(NEAR.checked_div(&usdc.rescale(0).unwrap(), 8), None);
+        assert_eq!(NEAR.checked_div(&NEAR, 77), None);
+    }
+
+    #[test]
+    fn test_rescale_at_max_u128_decimals() {
+        let one = Price {
+            multiplier: 1,
+            decimals: 0,
+            negative: false,
+        };
+        let price = one.rescale(MAX_U128_DECIMALS).unwrap();
+        assert_eq!(price.multiplier, 10u128.pow(38));
+        assert_eq!(one.rescale(MAX_U128_DECIMALS + 1), None);
+        let four = Price {
+            multiplier: 4,
+            decimals: 0,
+            negative: false,
+        };
+        assert_eq!(four.rescale(MAX_U128_DECIMALS), None);
+
+        let tiny = Price {
+            multiplier: u128::MAX,
+            decimals: MAX_VALID_DECIMALS,
+            negative: false,
+        };
+        assert_eq!(tiny.rescale(0).unwrap().multiplier, 0);
+        assert_eq!(tiny.rescale(MAX_VALID_DECIMALS - MAX_U128_DECIMALS).unwrap().multiplier, 3);
+    }
+
+    #[test]
+    fn test_negative_price_ordering() {
+        let negative = Price {
+            multiplier: 5,
+            decimals: 2,
+            negative: true,
+        };
+        let more_negative = Price {
+            multiplier: 1,
+            decimals: 0,
+            negative: true,
+        };
+        let negative_zero = Price {
+            multiplier: 0,
+            decimals: 0,
+            negative: true,
+        };
+        assert!(negative < DAI);
+        assert!(more_negative < negative);
+        assert_eq!(
+            negative_zero,
+            Price {
+                multiplier: 0,
+                decimals: 4,
+                negative: false,
+            }
+        );
+        let (lower, upper) = negative.deviation_band(1000).unwrap();
+        assert!(lower < negative && negative < upper);
+    }
+
+    #[test]
+    fn test_negative_price_serialization() {
+        let negative = Price {
+            multiplier: 5,
+            decimals: 2,
+            negative: true,
+        };
+        let json = near_sdk::serde_json::to_string(&negative).unwrap();
+        assert_eq!(json, r#"{"multiplier":5,"decimals":2,"negative":true}"#);
+        let parsed: Price = near_sdk::serde_json::from_str(&json).unwrap();
+        assert!(parsed.negative);
+        assert_eq!(parsed, negative);
+
+        let json = near_sdk::serde_json::to_string(&DAI).unwrap();
+        assert_eq!(json, r#"{"multiplier":101,"decimals":20}"#);
+        let parsed: Price = near_sdk::serde_json::from_str(&json).unwrap();
+        assert!(!parsed.negative);
+    }
+}
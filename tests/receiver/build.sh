@@ -0,0 +1,7 @@
+#!/bin/bash
+
+# The prebuilt std of current toolchains uses wasm features that the NEAR runtime rejects, so
+# rebuild std for the MVP target.
+RUSTFLAGS='-C link-arg=-s -C target-cpu=mvp -C link-arg=--allow-undefined' \
+    cargo +nightly build -Zbuild-std=std,panic_abort --target wasm32-unknown-unknown --release
+cp target/wasm32-unknown-unknown/release/test_receiver.wasm ../../res/
@@ -0,0 +1,32 @@
+//! A minimal `oracle_on_call` receiver for the workspace tests. It records the last call and
+//! panics if the msg is "panic", so the tests can cover both outcomes of the oracle's callback.
+
+use near_sdk::serde_json::Value;
+use near_sdk::{env, near, AccountId, PanicOnDefault};
+
+#[near(contract_state)]
+#[derive(PanicOnDefault)]
+pub struct Contract {
+    pub last_sender_id: Option<AccountId>,
+    pub last_msg: Option<String>,
+}
+
+#[near]
+impl Contract {
+    #[init]
+    pub fn new() -> Self {
+        Self { last_sender_id: None, last_msg: None }
+    }
+
+    pub fn oracle_on_call(&mut self, sender_id: AccountId, data: Value, msg: String) {
+        assert_ne!(msg, "panic", "Receiver panicked on request");
+        assert!(data.is_object(), "Expected the price data");
+        env::log_str(&format!("oracle_on_call from {} with {}", sender_id, msg));
+        self.last_sender_id = Some(sender_id);
+        self.last_msg = Some(msg);
+    }
+
+    pub fn get_last_msg(&self) -> Option<String> {
+        self.last_msg.clone()
+    }
+}
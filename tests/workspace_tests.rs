@@ -1,10 +1,11 @@
 use near_workspaces::{Account, AccountId, Contract, Worker, network::Sandbox};
 use near_workspaces::types::{NearToken};
 use serde_json::json;
-use anyhow;
 
 // Test constants
 const CONTRACT_WASM_PATH: &str = "res/price_oracle.wasm";
+// Built from tests/receiver with its build.sh
+const RECEIVER_WASM_PATH: &str = "res/test_receiver.wasm";
 
 // Helper function to create test environment
 async fn setup_test_env() -> anyhow::Result<(Worker<Sandbox>, Account, Account, Account, Contract)> {
@@ -22,11 +23,9 @@ async fn setup_test_env() -> anyhow::Result<(Worker<Sandbox>, Account, Account,
     
     // Initialize the contract
     let init_result = contract
-        .call("new")
+        .call("init")
         .args_json(json!({
-            "recency_duration_sec": 3600,
-            "owner_id": owner.id(),
-            "near_claim_amount": "1000000000000000000000000" // 1 NEAR
+            "owner_id": owner.id()
         }))
         .transact()
         .await?;
@@ -36,6 +35,14 @@ async fn setup_test_env() -> anyhow::Result<(Worker<Sandbox>, Account, Account,
     Ok((worker, owner, oracle, user, contract))
 }
 
+// Helper function to deploy an `oracle_on_call` receiver
+async fn deploy_receiver(worker: &Worker<Sandbox>) -> anyhow::Result<Contract> {
+    let wasm = std::fs::read(RECEIVER_WASM_PATH)?;
+    let receiver = worker.dev_deploy(&wasm).await?;
+    receiver.call("new").transact().await?.into_result()?;
+    Ok(receiver)
+}
+
 #[tokio::test]
 async fn test_contract_initialization() -> anyhow::Result<()> {
     let (_worker, owner, _oracle, _user, contract) = setup_test_env().await?;
@@ -712,4 +719,84 @@ async fn test_register_agent_and_report_prices_flow() -> anyhow::Result<()> {
     // After successful registration, oracle would be able to report prices
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_oracle_call_batch() -> anyhow::Result<()> {
+    let (worker, _owner, _oracle, user, contract) = setup_test_env().await?;
+    let first = deploy_receiver(&worker).await?;
+    let second = deploy_receiver(&worker).await?;
+
+    let result = user
+        .call(contract.id(), "oracle_call_batch")
+        .args_json(json!({
+            "receivers": [[first.id(), "first"], [second.id(), "second"]],
+            "asset_ids": null,
+            "callback_gas": null
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+
+    assert!(result.is_success());
+    let results: Vec<bool> = result.json()?;
+    assert_eq!(results, vec![true, true]);
+
+    // Both receivers got their own msg
+    for (receiver, msg) in [(&first, "first"), (&second, "second")] {
+        let last_msg: Option<String> = receiver.call("get_last_msg").view().await?.json()?;
+        assert_eq!(last_msg.as_deref(), Some(msg));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_oracle_call_reports_receiver_failure() -> anyhow::Result<()> {
+    let (worker, _owner, _oracle, user, contract) = setup_test_env().await?;
+    let failing = deploy_receiver(&worker).await?;
+    let succeeding = deploy_receiver(&worker).await?;
+
+    // The receiver panics on "panic", so the callback reports the failure
+    let result = user
+        .call(contract.id(), "oracle_call")
+        .args_json(json!({
+            "receiver_id": failing.id(),
+            "asset_ids": null,
+            "msg": "panic",
+            "callback_gas": null
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+
+    assert!(result.is_success());
+    assert!(result.logs().iter().any(|log| log.contains("oracle_on_call failed")));
+    assert!(!result.json::<bool>()?);
+
+    // One failing receiver doesn't affect the result of the other
+    let result = user
+        .call(contract.id(), "oracle_call_batch")
+        .args_json(json!({
+            "receivers": [[failing.id(), "panic"], [succeeding.id(), "ok"]],
+            "asset_ids": null,
+            "callback_gas": null
+        }))
+        .deposit(NearToken::from_yoctonear(1))
+        .max_gas()
+        .transact()
+        .await?;
+
+    assert!(result.is_success());
+    let results: Vec<bool> = result.json()?;
+    assert_eq!(results, vec![false, true]);
+
+    let last_msg: Option<String> = failing.call("get_last_msg").view().await?.json()?;
+    assert_eq!(last_msg, None);
+    let last_msg: Option<String> = succeeding.call("get_last_msg").view().await?.json()?;
+    assert_eq!(last_msg.as_deref(), Some("ok"));
+
+    Ok(())
+}